@@ -0,0 +1,302 @@
+use crate::minecraft::libraries::{Argument, Rule};
+use crate::rules::{self, Target};
+use launcher_extension_api::launcher::optional::{Action, ArgsAction, CompareMode, Optional};
+use launcher_extension_api::launcher::validation::OsType;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+/// Variables the launcher fills in itself, arguments using them are added by the launcher
+/// and dropped from the profile.
+const LAUNCHER_VARIABLES: [&str; 20] = [
+    "auth_player_name",
+    "auth_session",
+    "auth_uuid",
+    "auth_access_token",
+    "auth_xuid",
+    "clientid",
+    "user_type",
+    "user_properties",
+    "version_name",
+    "version_type",
+    "game_directory",
+    "game_assets",
+    "assets_root",
+    "assets_index_name",
+    "natives_directory",
+    "launcher_name",
+    "launcher_version",
+    "classpath",
+    "resolution_width",
+    "resolution_height",
+];
+
+/// Profile arguments shared by all platforms and the optionals that add the rest
+/// on the platforms they apply to.
+pub struct ResolvedArguments {
+    pub jvm: Vec<String>,
+    pub game: Vec<String>,
+    pub optionals: Vec<Optional>,
+}
+
+/// Arguments that belong together, a plain flag is kept with the value following it.
+struct ArgumentUnit<'a> {
+    rules: Option<&'a [Rule]>,
+    values: Vec<&'a str>,
+}
+
+#[derive(Clone, Copy)]
+enum ArgumentKind {
+    Jvm,
+    Game,
+}
+
+/// Merges vanilla and loader arguments in that order. Rules are evaluated for every
+/// launcher platform, arguments that end up the same everywhere go into the profile.
+/// `library_paths` maps the maven path of every profile library to its path in the
+/// libraries folder, `${library_directory}/<maven path>` resolves to the latter.
+pub fn resolve(jvm: &[&Argument], game: &[&Argument], library_paths: &HashMap<String, String>) -> ResolvedArguments {
+    let targets = rules::targets();
+    let jvm_units = units(jvm);
+    let game_units = units(game);
+    let mut resolved = ResolvedArguments {
+        jvm: vec![],
+        game: vec![],
+        optionals: vec![],
+    };
+    let mut platform_args: Vec<(OsType, Vec<String>, Vec<String>)> = targets
+        .iter()
+        .map(|(os_type, _)| (os_type.clone(), vec![], vec![]))
+        .collect();
+    for (kind, units) in [(ArgumentKind::Jvm, &jvm_units), (ArgumentKind::Game, &game_units)].iter() {
+        for unit in units.iter() {
+            let per_target: Vec<Option<Vec<String>>> = targets
+                .iter()
+                .map(|(_, target)| resolve_unit(unit, target, *kind, library_paths))
+                .collect();
+            let common = per_target.windows(2).all(|pair| pair[0] == pair[1]);
+            if common {
+                if let Some(Some(values)) = per_target.into_iter().next() {
+                    match kind {
+                        ArgumentKind::Jvm => resolved.jvm.extend(values),
+                        ArgumentKind::Game => resolved.game.extend(values),
+                    }
+                }
+                continue;
+            }
+            for (values, (_, jvm_args, game_args)) in per_target.into_iter().zip(platform_args.iter_mut()) {
+                if let Some(values) = values {
+                    match kind {
+                        ArgumentKind::Jvm => jvm_args.extend(values),
+                        ArgumentKind::Game => game_args.extend(values),
+                    }
+                }
+            }
+        }
+    }
+    for (os_type, jvm_args, client_args) in platform_args {
+        if jvm_args.is_empty() && client_args.is_empty() {
+            continue;
+        }
+        resolved.optionals.push(Optional {
+            actions: vec![Action::Args(ArgsAction { jvm_args, client_args })],
+            rules: vec![rules::os_rule(os_type, CompareMode::Equal)],
+            enabled: true,
+            visible: false,
+            description: None,
+            name: None,
+        });
+    }
+    resolved
+}
+
+fn units<'a>(arguments: &[&'a Argument]) -> Vec<ArgumentUnit<'a>> {
+    let mut units: Vec<ArgumentUnit> = Vec::new();
+    for argument in arguments {
+        match argument {
+            Argument::Plain(value) => {
+                if let Some(last) = units.last_mut() {
+                    if last.rules.is_none() && last.values.len() == 1 && is_flag(last.values[0]) && !value.starts_with('-') {
+                        last.values.push(value);
+                        continue;
+                    }
+                }
+                units.push(ArgumentUnit {
+                    rules: None,
+                    values: vec![value],
+                });
+            }
+            Argument::Conditional { rules, value } => units.push(ArgumentUnit {
+                rules: Some(rules),
+                values: value.values(),
+            }),
+        }
+    }
+    units
+}
+
+fn is_flag(value: &str) -> bool {
+    value.starts_with('-') && !value.contains('=')
+}
+
+/// Values of the unit on one platform, `None` if its rules don't apply there or the
+/// launcher adds it itself.
+fn resolve_unit(
+    unit: &ArgumentUnit,
+    target: &Target,
+    kind: ArgumentKind,
+    library_paths: &HashMap<String, String>,
+) -> Option<Vec<String>> {
+    let allowed = unit
+        .rules
+        .map(|rules| rules::is_allowed(rules, target, &HashMap::new()))
+        .unwrap_or(true);
+    if !allowed {
+        return None;
+    }
+    let regex = Regex::new(r"\$\{(\w+)\}").unwrap();
+    let library_regex = Regex::new(r"\$\{library_directory\}/([^$,\s]+)").unwrap();
+    let mut launcher_supplied = false;
+    let values = unit
+        .values
+        .iter()
+        .map(|value| {
+            let value = library_regex.replace_all(value, |captures: &Captures| {
+                let path = library_paths.get(&captures[1]).map(|path| path.as_str()).unwrap_or(&captures[1]);
+                format!("${{library_directory}}/{}", path)
+            });
+            regex
+                .replace_all(&value, |captures: &Captures| match profile_variable(&captures[1], target, kind) {
+                    Some(value) => value.to_string(),
+                    None => {
+                        launcher_supplied |= LAUNCHER_VARIABLES.contains(&&captures[1])
+                            || captures[1].starts_with("quickPlay");
+                        captures[0].to_string()
+                    }
+                })
+                .to_string()
+        })
+        .collect();
+    if launcher_supplied {
+        None
+    } else {
+        Some(values)
+    }
+}
+
+/// Variables known when generating the profile. Forge lists the client jar in JVM
+/// arguments by version name, the profile always calls it `minecraft.jar`.
+fn profile_variable(name: &str, target: &Target, kind: ArgumentKind) -> Option<&'static str> {
+    match (name, kind) {
+        ("classpath_separator", _) if target.os_name == "windows" => Some(";"),
+        ("classpath_separator", _) => Some(":"),
+        ("library_directory", _) => Some("libraries"),
+        ("version_name", ArgumentKind::Jvm) => Some("minecraft"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use launcher_extension_api::launcher::optional::Rule as LauncherRule;
+    use std::mem::discriminant;
+
+    fn parse_arguments(json: &str) -> Vec<Argument> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn resolve_game(json: &str) -> ResolvedArguments {
+        let game = parse_arguments(json);
+        resolve(&[], &game.iter().collect::<Vec<_>>(), &HashMap::new())
+    }
+
+    #[test]
+    fn flag_is_dropped_with_launcher_supplied_value() {
+        let resolved = resolve_game(
+            r#"["--username", "${auth_player_name}", "--version", "${version_name}", "--launchTarget", "forgeclient"]"#,
+        );
+        assert_eq!(resolved.game, vec!["--launchTarget", "forgeclient"]);
+        assert!(resolved.optionals.is_empty());
+    }
+
+    #[test]
+    fn flags_without_value_stay_separate() {
+        let resolved = resolve_game(r#"["--demo", "--fullscreen", "--width", "${resolution_width}"]"#);
+        assert_eq!(resolved.game, vec!["--demo", "--fullscreen"]);
+    }
+
+    #[test]
+    fn quick_play_variables_are_launcher_supplied() {
+        let resolved = resolve_game(r#"["--quickPlayPath", "${quickPlayPath}", "--tweakClass", "a.B"]"#);
+        assert_eq!(resolved.game, vec!["--tweakClass", "a.B"]);
+    }
+
+    #[test]
+    fn feature_rules_never_apply() {
+        let resolved = resolve_game(
+            r#"[{"rules": [{"action": "allow", "features": {"is_demo_user": true}}], "value": "--demo"}]"#,
+        );
+        assert!(resolved.game.is_empty());
+        assert!(resolved.optionals.is_empty());
+    }
+
+    fn platform_args(resolved: &ResolvedArguments, os_type: OsType) -> Option<&ArgsAction> {
+        resolved
+            .optionals
+            .iter()
+            .find(|optional| {
+                matches!(
+                    &optional.rules[0],
+                    LauncherRule::OsType(rule) if discriminant(&rule.os_type) == discriminant(&os_type)
+                )
+            })
+            .map(|optional| match &optional.actions[0] {
+                Action::Args(args) => args,
+                _ => panic!("expected arguments action"),
+            })
+    }
+
+    #[test]
+    fn os_specific_arguments_become_optionals() {
+        let jvm = parse_arguments(
+            r#"[
+                {"rules": [{"action": "allow", "os": {"name": "osx"}}], "value": ["-XstartOnFirstThread"]},
+                {"rules": [{"action": "allow", "os": {"arch": "x86"}}], "value": "-Xss1M"},
+                "-Djava.library.path=${natives_directory}",
+                "-cp",
+                "${classpath}",
+                "-DignoreList=x,${version_name}.jar"
+            ]"#,
+        );
+        let resolved = resolve(&jvm.iter().collect::<Vec<_>>(), &[], &HashMap::new());
+        assert_eq!(resolved.jvm, vec!["-DignoreList=x,minecraft.jar"]);
+        assert_eq!(resolved.optionals.len(), 3);
+        assert_eq!(platform_args(&resolved, OsType::MacOsX64).unwrap().jvm_args, vec!["-XstartOnFirstThread"]);
+        assert_eq!(platform_args(&resolved, OsType::WindowsX32).unwrap().jvm_args, vec!["-Xss1M"]);
+        assert_eq!(platform_args(&resolved, OsType::LinuxX32).unwrap().jvm_args, vec!["-Xss1M"]);
+        assert!(platform_args(&resolved, OsType::LinuxX64).is_none());
+    }
+
+    #[test]
+    fn library_directory_uses_profile_library_paths() {
+        let jvm = parse_arguments(r#"["-p", "${library_directory}/a/a.jar${classpath_separator}${library_directory}/b/b.jar"]"#);
+        let mut library_paths = HashMap::new();
+        library_paths.insert("a/a.jar".to_string(), "a.jar".to_string());
+        let resolved = resolve(&jvm.iter().collect::<Vec<_>>(), &[], &library_paths);
+        assert!(resolved.jvm.is_empty());
+        assert_eq!(
+            platform_args(&resolved, OsType::WindowsX64).unwrap().jvm_args,
+            vec!["-p", "libraries/a.jar;libraries/b/b.jar"]
+        );
+        assert_eq!(
+            platform_args(&resolved, OsType::LinuxX64).unwrap().jvm_args,
+            vec!["-p", "libraries/a.jar:libraries/b/b.jar"]
+        );
+    }
+
+    #[test]
+    fn version_name_is_only_resolved_in_jvm_arguments() {
+        let resolved = resolve_game(r#"["--fml.mcVersion", "${version_name}"]"#);
+        assert!(resolved.game.is_empty());
+    }
+}
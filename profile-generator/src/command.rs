@@ -1,10 +1,22 @@
 use launcher_extension_api::command::ExtensionCommandExecutor;
 use clap::{App, AppSettings, Arg};
 use crate::{validator, generator};
-use crate::minecraft::version::Libraries;
+use crate::minecraft::version::{resolve_version_id, Libraries, PartialVersion};
 use crate::minecraft::fabric::FabricLoaderManifest;
 use crate::minecraft::forge::ForgeManifest;
+use crate::minecraft::installer::ForgeInstaller;
+use crate::minecraft::liteloader::LiteLoader;
+use crate::minecraft::neoforge::NeoForgeManifest;
+use crate::minecraft::quilt::QuiltLoaderManifest;
 use crate::minecraft::GameType;
+use crate::download::Downloader;
+use crate::config::{Config, CONFIG_PATH};
+use crate::generator::ProfileOptions;
+use launcher_extension_api::launcher::config::Configurable;
+use std::path::{Path, PathBuf};
+use crate::progress::{Progress, ProgressFormat};
+use crate::modpack::{curseforge, instance, modrinth};
+use crate::mods::ModsFolder;
 
 pub struct ProfileGenerationCommand<'a> {
     app: App<'a>
@@ -20,10 +32,10 @@ impl Default for ProfileGenerationCommand<'_> {
             .arg(
                 Arg::new("version")
                     .short('v')
-                    .required(true)
+                    .required_unless_present_any(["mrpack", "curseforge", "importInstance", "versionJson", "versionFile"])
                     .long("version")
                     .takes_value(true)
-                    .about("Minecraft Version"),
+                    .about("Minecraft Version, latest or latest-snapshot, must match a version json"),
             )
             .arg(
                 Arg::new("profileName")
@@ -72,6 +84,196 @@ impl Default for ProfileGenerationCommand<'_> {
                     .takes_value(true)
                     .conflicts_with("forge")
                     .validator(validator::correct_fabric_version),
+            )
+            .arg(
+                Arg::new("quilt")
+                    .about("Quilt Loader Version")
+                    .long("quilt")
+                    .takes_value(true)
+                    .conflicts_with_all(&["forge", "fabric"])
+                    .validator(validator::correct_quilt_version),
+            )
+            .arg(
+                Arg::new("neoforge")
+                    .about("NeoForge Version")
+                    .long("neoforge")
+                    .takes_value(true)
+                    .conflicts_with_all(&["forge", "fabric", "quilt"])
+                    .validator(validator::correct_neoforge_version),
+            )
+            .arg(
+                Arg::new("forgeInstaller")
+                    .about("Forge 1.13+ installer jar or version to download it for")
+                    .long("forge-installer")
+                    .takes_value(true)
+                    .conflicts_with_all(&["forge", "fabric", "quilt", "neoforge"])
+                    .validator(validator::correct_forge_installer),
+            )
+            .arg(
+                Arg::new("versionJson")
+                    .about("Version JSON file or url, merged with the versions it inheritsFrom, looked up next to a local file before Mojang")
+                    .long("version-json")
+                    .takes_value(true)
+                    .conflicts_with_all(&["forge", "fabric", "quilt", "neoforge", "forgeInstaller"]),
+            )
+            .arg(
+                Arg::new("versionFile")
+                    .about("Local version JSON file, like --version-json")
+                    .long("version-file")
+                    .takes_value(true)
+                    .conflicts_with_all(&["forge", "fabric", "quilt", "neoforge", "forgeInstaller", "versionJson"])
+                    .validator(validator::correct_file),
+            )
+            .arg(
+                Arg::new("mrpack")
+                    .about("Modrinth modpack, sets the version and loader")
+                    .long("mrpack")
+                    .takes_value(true)
+                    .conflicts_with_all(&[
+                        "version",
+                        "versionJson",
+                        "versionFile",
+                        "forge",
+                        "fabric",
+                        "quilt",
+                        "neoforge",
+                        "forgeInstaller",
+                    ])
+                    .validator(validator::correct_file),
+            )
+            .arg(
+                Arg::new("curseforge")
+                    .about("CurseForge modpack export, sets the version and loader")
+                    .long("curseforge")
+                    .takes_value(true)
+                    .conflicts_with_all(&[
+                        "version",
+                        "mrpack",
+                        "versionJson",
+                        "versionFile",
+                        "forge",
+                        "fabric",
+                        "quilt",
+                        "neoforge",
+                        "forgeInstaller",
+                    ])
+                    .validator(validator::correct_file),
+            )
+            .arg(
+                Arg::new("importInstance")
+                    .about("MultiMC or Prism Launcher instance folder or zip, sets the version and loader")
+                    .long("import-instance")
+                    .takes_value(true)
+                    .conflicts_with_all(&[
+                        "version",
+                        "mrpack",
+                        "curseforge",
+                        "versionJson",
+                        "versionFile",
+                        "forge",
+                        "fabric",
+                        "quilt",
+                        "neoforge",
+                        "forgeInstaller",
+                    ])
+                    .validator(validator::correct_path),
+            )
+            .arg(
+                Arg::new("mods")
+                    .about("Mods folder copied into the profile")
+                    .long("mods")
+                    .takes_value(true)
+                    .validator(validator::correct_folder),
+            )
+            .arg(
+                Arg::new("modsManifest")
+                    .about("JSON marking mods of the mods folder as optional, with name and description")
+                    .long("mods-manifest")
+                    .takes_value(true)
+                    .requires("mods")
+                    .validator(validator::correct_file),
+            )
+            .arg(
+                Arg::new("tweakClass")
+                    .about("LaunchWrapper tweak class, may be repeated, applied in the given order")
+                    .long("tweak-class")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .conflicts_with_all(&["fabric", "quilt", "neoforge", "forgeInstaller"]),
+            )
+            .arg(
+                Arg::new("liteloader")
+                    .about("LiteLoader Version or latest")
+                    .long("liteloader")
+                    .takes_value(true)
+                    .conflicts_with_all(&["fabric", "quilt", "neoforge", "forgeInstaller"])
+                    .validator(validator::correct_liteloader_version),
+            )
+            .arg(
+                Arg::new("workers")
+                    .about("Number of concurrent downloads (default from config/profilegen.json)")
+                    .short('w')
+                    .long("workers")
+                    .takes_value(true)
+                    .validator(validator::correct_workers),
+            )
+            .arg(
+                Arg::new("assetWorkers")
+                    .about("Number of concurrent asset downloads (default from config/profilegen.json)")
+                    .long("asset-workers")
+                    .takes_value(true)
+                    .validator(validator::correct_workers),
+            )
+            .arg(
+                Arg::new("retries")
+                    .about("Number of retries for failed downloads (default from config/profilegen.json)")
+                    .long("retries")
+                    .takes_value(true)
+                    .validator(validator::correct_retries),
+            )
+            .arg(
+                Arg::new("progress")
+                    .about("Progress output format")
+                    .long("progress")
+                    .takes_value(true)
+                    .possible_values(&["console", "json"])
+                    .default_value("console"),
+            )
+            .arg(
+                Arg::new("output")
+                    .about("Output root folder (default from config/profilegen.json)")
+                    .short('o')
+                    .long("output")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::new("nativesDir")
+                    .about("Natives folder, relative to the output root")
+                    .long("natives-dir")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::new("assetsDir")
+                    .about("Assets folder, relative to the output root")
+                    .long("assets-dir")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::new("profilesDir")
+                    .about("Profiles folder, relative to the output root")
+                    .long("profiles-dir")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::new("librariesDir")
+                    .about("Libraries folder, relative to the output root")
+                    .long("libraries-dir")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::new("dryRun")
+                    .about("Resolve metadata and print what would be written, without downloading")
+                    .long("dry-run"),
             );
         Self {
             app
@@ -100,15 +302,74 @@ impl ExtensionCommandExecutor for ProfileGenerationCommand<'_> {
             return;
         };
         let assets = matches.value_of("assets");
-        let game_version = if let Some(val) = matches.value_of("version") {
-            val
+        let mut config = match Config::get_config(CONFIG_PATH.as_ref()) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("Can't load config: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = config.validate() {
+            println!("Invalid config: {}", e);
+            return;
+        }
+        let pack = if let Some(path) = matches.value_of("mrpack") {
+            Some(modrinth::read_mrpack(Path::new(path)))
+        } else if let Some(path) = matches.value_of("curseforge") {
+            Some(curseforge::read_curseforge(Path::new(path), &config.curseforge))
+        } else {
+            matches
+                .value_of("importInstance")
+                .map(|path| instance::read_instance(Path::new(path)))
+        };
+        let pack = match pack {
+            Some(Err(e)) => {
+                println!("Can't read modpack: {}", e);
+                return;
+            }
+            pack => pack.and_then(|v| v.ok()),
+        };
+        let version_json = match matches
+            .value_of("versionJson")
+            .or_else(|| matches.value_of("versionFile"))
+            .map(|location| {
+                let path = Path::new(location);
+                let local_folder = if path.is_file() { path.parent() } else { None };
+                PartialVersion::load(location).and_then(|version| version.resolve(local_folder))
+            }) {
+            Some(Err(e)) => {
+                println!("Can't resolve version json: {}", e);
+                return;
+            }
+            version_json => version_json.and_then(|v| v.ok()),
+        };
+        let cli_version = match matches.value_of("version").map(|val| (val, resolve_version_id(val))) {
+            Some((val, Err(e))) => {
+                println!("Can't resolve version {}: {}", val, e);
+                return;
+            }
+            cli_version => cli_version.and_then(|(_, id)| id.ok()),
+        };
+        let game_version = if let Some(pack) = &pack {
+            pack.game_version.clone()
+        } else if let Some(libraries) = &version_json {
+            let json_version = libraries.game_version.clone().unwrap_or_default();
+            match cli_version {
+                Some(version) if version != json_version => {
+                    println!("Version {} does not match {} of the version json", version, json_version);
+                    return;
+                }
+                _ => json_version,
+            }
+        } else if let Some(version) = cli_version {
+            version
         } else {
             println!("Can't get version");
             return;
         };
-        let game_libraries = if let Ok(val) = matches
-            .value_of_t::<Libraries>("version")
-        {
+        let game_libraries = if let Some(val) = version_json {
+            val
+        } else if let Ok(val) = game_version.parse::<Libraries>() {
             val
         } else {
             println!("Can't get libs");
@@ -128,24 +389,135 @@ impl ExtensionCommandExecutor for ProfileGenerationCommand<'_> {
             println!("Can't get server port");
             return;
         };
+        if let Some(val) = matches.value_of("output") {
+            config.output = PathBuf::from(val);
+        }
+        if let Some(val) = matches.value_of("nativesDir") {
+            config.natives = Some(PathBuf::from(val));
+        }
+        if let Some(val) = matches.value_of("assetsDir") {
+            config.assets = Some(PathBuf::from(val));
+        }
+        if let Some(val) = matches.value_of("profilesDir") {
+            config.profiles = Some(PathBuf::from(val));
+        }
+        if let Some(val) = matches.value_of("librariesDir") {
+            config.libraries = Some(PathBuf::from(val));
+        }
+        let mut options = config.get_download_options();
+        if let Ok(val) = matches.value_of_t::<usize>("workers") {
+            options.workers = val;
+        }
+        if let Ok(val) = matches.value_of_t::<usize>("assetWorkers") {
+            options.asset_workers = val;
+        }
+        if let Ok(val) = matches.value_of_t::<u32>("retries") {
+            options.retries = val;
+        }
+        let progress = if let Ok(val) = matches
+            .value_of_t::<ProgressFormat>("progress") {
+            Progress::new(val.reporter())
+        } else {
+            println!("Can't get progress format");
+            return;
+        };
+        let downloader = Downloader::new(options, progress);
         let fabric = matches.value_of_t::<FabricLoaderManifest>("fabric");
         let forge = matches.value_of_t::<ForgeManifest>("forge");
-        let game_type = if let Ok(manifest) = fabric {
+        let quilt = matches.value_of_t::<QuiltLoaderManifest>("quilt");
+        let neoforge = match matches.value_of("neoforge").map(|v| v.parse::<NeoForgeManifest>()) {
+            Some(Err(e)) => {
+                println!("Can't read neoforge installer: {}", e);
+                return;
+            }
+            neoforge => neoforge.and_then(|v| v.ok()),
+        };
+        let forge_installer = match matches.value_of("forgeInstaller").map(|v| v.parse::<ForgeInstaller>()) {
+            Some(Err(e)) => {
+                println!("Can't read forge installer: {}", e);
+                return;
+            }
+            installer => installer.and_then(|v| v.ok()),
+        };
+        let game_type = if let Some(pack) = &pack {
+            match pack.game_type() {
+                Ok(game_type) => game_type,
+                Err(e) => {
+                    println!("Can't resolve modpack loader: {}", e);
+                    return;
+                }
+            }
+        } else if let Ok(manifest) = fabric {
             GameType::Fabric(manifest)
         } else if let Ok(manifest) = forge {
             GameType::Forge(manifest)
+        } else if let Ok(manifest) = quilt {
+            GameType::Quilt(manifest)
+        } else if let Some(manifest) = neoforge {
+            GameType::NeoForge(manifest)
+        } else if let Some(installer) = forge_installer {
+            GameType::ForgeInstaller(installer)
         } else {
             GameType::Vanilla
         };
-        match generator::generate_profile(
-            profile_name,
-            game_version,
-            game_libraries,
+        let tweak_classes: Vec<String> = matches
+            .values_of("tweakClass")
+            .map(|values| values.map(|v| v.to_string()).collect())
+            .unwrap_or_default();
+        let launchwrapper = !tweak_classes.is_empty() || matches.is_present("liteloader");
+        if launchwrapper && !generator::supports_launchwrapper(&game_type, &game_libraries) {
+            println!("Tweak classes and liteloader need vanilla or a LaunchWrapper based Forge");
+            return;
+        }
+        let liteloader = match matches.value_of("liteloader").map(|v| LiteLoader::resolve(&game_version, v)) {
+            Some(Err(e)) => {
+                println!("Can't resolve liteloader: {}", e);
+                return;
+            }
+            liteloader => liteloader.and_then(|v| v.ok()),
+        };
+        let mods = match matches
+            .value_of("mods")
+            .map(|v| ModsFolder::read(Path::new(v), matches.value_of("modsManifest").map(Path::new)))
+        {
+            Some(Err(e)) => {
+                println!("Can't read mods: {}", e);
+                return;
+            }
+            mods => mods.and_then(|v| v.ok()),
+        };
+        let profile = ProfileOptions {
+            name: profile_name,
+            version: &game_version,
             address,
             port,
+            assets,
+            java: &config.java,
+            mirrors: &config.mirrors,
+            tweak_classes: &tweak_classes,
+            liteloader: liteloader.as_ref(),
+            pack: pack.as_ref(),
+            mods: mods.as_ref(),
+        };
+        let plan = match generator::plan_profile(
+            &profile,
+            game_libraries,
             game_type,
-            assets
+            &config.get_folders(),
         ) {
+            Ok(plan) => plan,
+            Err(e) => {
+                println!("Can't resolve profile: {}", e);
+                return;
+            }
+        };
+        if matches.is_present("dryRun") {
+            if let Err(e) = plan.print() {
+                println!("Can't print profile plan: {}", e);
+            }
+            return;
+        }
+        match plan.execute(&downloader) {
             Err(e) => {
                 println!("Can't generate profile: {}", e)
             }
@@ -154,4 +526,4 @@ impl ExtensionCommandExecutor for ProfileGenerationCommand<'_> {
             }
         }
     }
-}
\ No newline at end of file
+}
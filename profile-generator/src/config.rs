@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use launcher_extension_api::launcher::config::Configurable;
+use crate::modpack::curseforge::CurseForgeConfig;
+use crate::download::{DownloadOptions, DEFAULT_ASSET_WORKERS, DEFAULT_RETRIES, DEFAULT_WORKERS, MAX_RETRIES};
+use launcher_extension_api::Result;
+
+pub const CONFIG_PATH: &str = "config/profilegen.json";
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub output: PathBuf,
+    pub natives: Option<PathBuf>,
+    pub assets: Option<PathBuf>,
+    pub profiles: Option<PathBuf>,
+    pub libraries: Option<PathBuf>,
+    pub workers: usize,
+    pub asset_workers: usize,
+    pub retries: u32,
+    /// Java used to run Forge installer processors.
+    pub java: PathBuf,
+    /// Url prefixes replaced in every download, e.g. `https://libraries.minecraft.net/`
+    /// to a local repository mirror.
+    pub mirrors: BTreeMap<String, String>,
+    pub curseforge: CurseForgeConfig,
+}
+
+impl Configurable for Config {}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            output: PathBuf::from("static"),
+            natives: None,
+            assets: None,
+            profiles: None,
+            libraries: None,
+            workers: DEFAULT_WORKERS,
+            asset_workers: DEFAULT_ASSET_WORKERS,
+            retries: DEFAULT_RETRIES,
+            java: PathBuf::from("java"),
+            mirrors: BTreeMap::new(),
+            curseforge: CurseForgeConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Sub-folders default to the usual launcher layout under the output root,
+    /// relative overrides are resolved against the root as well.
+    pub fn get_folders(&self) -> OutputFolders {
+        let folder = |custom: &Option<PathBuf>, default: &str| {
+            self.output.join(custom.as_ref().map(|v| v.as_path()).unwrap_or_else(|| default.as_ref()))
+        };
+        OutputFolders {
+            root: self.output.clone(),
+            natives: folder(&self.natives, "natives"),
+            assets: folder(&self.assets, "assets"),
+            profiles: folder(&self.profiles, "profiles"),
+            libraries: folder(&self.libraries, "libraries"),
+        }
+    }
+
+    /// Applies the limits the command line enforces to values read from the file.
+    pub fn validate(&self) -> Result<()> {
+        if self.workers == 0 || self.asset_workers == 0 {
+            return Err(launcher_extension_api::anyhow!("workers and asset_workers must be positive"));
+        }
+        if self.retries > MAX_RETRIES {
+            return Err(launcher_extension_api::anyhow!("retries must be a number from 0 to {}", MAX_RETRIES));
+        }
+        Ok(())
+    }
+
+    pub fn get_download_options(&self) -> DownloadOptions {
+        DownloadOptions {
+            workers: self.workers,
+            asset_workers: self.asset_workers,
+            retries: self.retries,
+            ..DownloadOptions::default()
+        }
+    }
+}
+
+pub struct OutputFolders {
+    pub root: PathBuf,
+    pub natives: PathBuf,
+    pub assets: PathBuf,
+    pub profiles: PathBuf,
+    pub libraries: PathBuf,
+}
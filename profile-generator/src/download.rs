@@ -1,56 +1,378 @@
-use std::fs::{create_dir_all, File};
+use std::fs::{create_dir_all, remove_file, rename, File, OpenOptions};
 use std::path::{Path, PathBuf};
 use launcher_extension_api::{Result, Error, Context};
-use std::{io, thread};
+use std::{io, iter, thread};
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use reqwest::blocking::Client;
+use reqwest::header::RANGE;
 use reqwest::StatusCode;
-use std::thread::JoinHandle;
-
-pub fn download_file<P: AsRef<Path>>(url: &str, path: P) -> Result<()> {
-    create_dir_all(&path)?;
-    let url_parts: Vec<&str> = url.split('/').collect();
-    let output = path.as_ref().join(url_parts.last().context("path is empty")?);
-    match reqwest::blocking::get(url) {
-        Ok(mut resp) => {
-            match resp.status() {
-                StatusCode::OK => (),
-                _ => {
-                    return Err(launcher_extension_api::anyhow!("Could not download this file: {}", url));
+use std::time::Duration;
+use crate::util::{file_sha1, file_sha512};
+use crate::progress::{Phase, Progress};
+
+pub const DEFAULT_WORKERS: usize = 4;
+pub const DEFAULT_ASSET_WORKERS: usize = 8;
+pub const DEFAULT_RETRIES: u32 = 3;
+pub const MAX_RETRIES: u32 = 10;
+
+#[derive(Clone, Debug)]
+pub struct DownloadOptions {
+    pub workers: usize,
+    pub asset_workers: usize,
+    pub retries: u32,
+    /// Delay before the first retry, doubled on every next one.
+    pub retry_delay: Duration,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            workers: DEFAULT_WORKERS,
+            asset_workers: DEFAULT_ASSET_WORKERS,
+            retries: DEFAULT_RETRIES,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Download {
+    pub url: String,
+    pub path: PathBuf,
+    pub sha1: Option<String>,
+    /// Checked in addition to `sha1`, modpack indexes publish both.
+    pub sha512: Option<String>,
+    pub size: Option<u64>,
+    /// Tried in order when `url` fails, e.g. the other mirrors a modpack lists.
+    pub fallback_urls: Vec<String>,
+}
+
+impl Download {
+    pub fn new<P: AsRef<Path>>(url: &str, path: P, sha1: Option<&str>) -> Self {
+        Download {
+            url: url.to_string(),
+            path: path.as_ref().to_path_buf(),
+            sha1: sha1.map(|v| v.to_string()),
+            sha512: None,
+            size: None,
+            fallback_urls: vec![],
+        }
+    }
+
+    pub fn with_sha512(mut self, sha512: Option<&str>) -> Self {
+        self.sha512 = sha512.map(|v| v.to_lowercase());
+        self
+    }
+
+    pub fn with_size(mut self, size: Option<u64>) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_fallback_urls(mut self, urls: &[String]) -> Self {
+        self.fallback_urls = urls.to_vec();
+        self
+    }
+
+    pub fn to_folder<P: AsRef<Path>>(url: &str, folder: P, sha1: Option<&str>) -> Result<Self> {
+        let url_parts: Vec<&str> = url.split('/').collect();
+        let path = folder.as_ref().join(url_parts.last().context("path is empty")?);
+        Ok(Download::new(url, path, sha1))
+    }
+}
+
+pub enum DownloadStatus {
+    Downloaded,
+    Reused,
+}
+
+#[derive(Default, Debug)]
+pub struct DownloadStats {
+    pub downloaded: usize,
+    pub reused: usize,
+}
+
+impl DownloadStats {
+    pub fn add(&mut self, status: DownloadStatus) {
+        match status {
+            DownloadStatus::Downloaded => self.downloaded += 1,
+            DownloadStatus::Reused => self.reused += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: DownloadStats) {
+        self.downloaded += other.downloaded;
+        self.reused += other.reused;
+    }
+}
+
+pub struct Downloader {
+    options: DownloadOptions,
+    progress: Progress,
+    /// Shared by all workers so connections are pooled.
+    client: Client,
+}
+
+impl Downloader {
+    pub fn new(options: DownloadOptions, progress: Progress) -> Self {
+        Downloader {
+            options,
+            progress,
+            client: Client::new(),
+        }
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Downloads all files of one generation phase, assets use their own worker count.
+    pub fn download_files(&self, phase: Phase, download: &[Download]) -> Result<DownloadStats> {
+        let workers = match phase {
+            Phase::Assets => self.options.asset_workers,
+            _ => self.options.workers,
+        };
+        let bytes_total = download.iter().filter_map(|v| v.size).sum();
+        self.progress.start_phase(phase, download.len() as u64, bytes_total);
+        let result = self.download_files_concurrent(download, workers);
+        self.progress.finish_phase();
+        result
+    }
+
+    fn download_file(&self, download: &Download) -> Result<DownloadStatus> {
+        // Files only reach their final path after verification, so one without a known
+        // hash is reused as it is and the remote hash is only looked up for downloads.
+        if download.path.is_file() {
+            let reuse = match &download.sha1 {
+                Some(sha1) => file_sha1(&download.path)?.eq_ignore_ascii_case(sha1),
+                None => true,
+            };
+            if reuse {
+                self.progress.add_bytes(download.path.metadata()?.len());
+                return Ok(DownloadStatus::Reused);
+            }
+        }
+        let expected = match &download.sha1 {
+            Some(sha1) => Some(sha1.to_lowercase()),
+            // sha512 already verifies the file, no need to ask the server.
+            None if download.sha512.is_some() => None,
+            None => remote_sha1(&self.client, &download.url),
+        };
+        if let Some(parent) = download.path.parent() {
+            create_dir_all(parent)?;
+        }
+        let part = part_path(&download.path);
+        let mut counted = 0;
+        let mut urls = iter::once(&download.url).chain(&download.fallback_urls).peekable();
+        while let Some(url) = urls.next() {
+            match self.fetch_with_retries(url, &part, &mut counted) {
+                Ok(()) => break,
+                Err(e) => match urls.peek() {
+                    Some(next) => {
+                        log::warn!("Download of {} failed ({}), trying {}", url, e, next);
+                        // A partial file from another server can't be resumed.
+                        if part.is_file() {
+                            remove_file(&part)?;
+                        }
+                        self.progress.sub_bytes(counted);
+                        counted = 0;
+                    }
+                    None => return Err(e),
+                },
+            }
+        }
+        match (&expected, &download.sha512) {
+            (None, None) => log::warn!("No checksum known for {}, skip verification", download.url),
+            _ => {
+                if let Some(expected) = &expected {
+                    verify_file(download, &part, expected, &file_sha1(&part)?)?;
+                }
+                if let Some(expected) = &download.sha512 {
+                    verify_file(download, &part, expected, &file_sha512(&part)?)?;
                 }
             }
-            let mut file = File::create(&output)?;
-            io::copy(&mut resp, &mut file)?;
         }
-        Err(err) => return Err(Error::from(err)),
-    };
-    Ok(())
+        rename(&part, &download.path)?;
+        Ok(DownloadStatus::Downloaded)
+    }
+
+    fn fetch_with_retries(&self, url: &str, part: &Path, counted: &mut u64) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match fetch(&self.client, url, part, &self.progress, counted) {
+                Ok(()) => return Ok(()),
+                Err(FetchError::Transient(e)) if attempt < self.options.retries => {
+                    let delay = self.options.retry_delay.saturating_mul(2u32.saturating_pow(attempt));
+                    log::warn!("Download of {} failed ({}), retry in {:?}", url, e, delay);
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(FetchError::Transient(e)) | Err(FetchError::Fatal(e)) => return Err(e),
+            }
+        }
+    }
+
+    fn download_files_concurrent(&self, download: &[Download], workers: usize) -> Result<DownloadStats> {
+        let next = AtomicUsize::new(0);
+        let results: Vec<(DownloadStats, Vec<Error>)> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..workers.max(1).min(download.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut stats = DownloadStats::default();
+                        let mut errors = Vec::new();
+                        while let Some(file) = download.get(next.fetch_add(1, Ordering::SeqCst)) {
+                            match self.download_file(file) {
+                                Ok(status) => stats.add(status),
+                                Err(e) => errors.push(e),
+                            }
+                            self.progress.file_done();
+                        }
+                        (stats, errors)
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| {
+                    thread.join().unwrap_or_else(|_| {
+                        (DownloadStats::default(), vec![launcher_extension_api::anyhow!("Download thread panicked")])
+                    })
+                })
+                .collect()
+        });
+        let mut stats = DownloadStats::default();
+        let mut errors = Vec::new();
+        for (worker_stats, mut worker_errors) in results {
+            stats.merge(worker_stats);
+            errors.append(&mut worker_errors);
+        }
+        if errors.is_empty() {
+            Ok(stats)
+        } else {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            Err(launcher_extension_api::anyhow!(
+                "{} of {} downloads failed:\n{}",
+                errors.len(),
+                download.len(),
+                messages.join("\n")
+            ))
+        }
+    }
 }
 
-pub fn download_files_single<P: AsRef<Path>>(download: &[(String, P)]) -> Result<()> {
-    for file in download {
-        download_file(&file.0, &file.1)?
+enum FetchError {
+    Transient(Error),
+    Fatal(Error),
+}
+
+impl From<io::Error> for FetchError {
+    fn from(err: io::Error) -> Self {
+        FetchError::Transient(Error::from(err))
+    }
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Downloads `url` into `part`, continuing from the end of `part` when it is already
+/// present and the server supports range requests. `counted` holds the bytes of this
+/// download already reported to `progress`.
+fn fetch(
+    client: &Client,
+    url: &str,
+    part: &Path,
+    progress: &Progress,
+    counted: &mut u64,
+) -> std::result::Result<(), FetchError> {
+    let offset = part.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
     }
+    let resp = request
+        .send()
+        .map_err(|e| FetchError::Transient(Error::from(e)))?;
+    let mut file = match resp.status() {
+        StatusCode::PARTIAL_CONTENT if offset > 0 => OpenOptions::new().append(true).open(part)?,
+        StatusCode::OK | StatusCode::PARTIAL_CONTENT => {
+            // The server ignored the range, earlier attempts are downloaded again.
+            progress.sub_bytes(*counted);
+            *counted = 0;
+            File::create(part)?
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            remove_file(part)?;
+            return Err(FetchError::Transient(launcher_extension_api::anyhow!(
+                "Partial file of {} is invalid",
+                url
+            )));
+        }
+        status if status.is_server_error() => {
+            return Err(FetchError::Transient(launcher_extension_api::anyhow!(
+                "Server responded with {} for {}",
+                status,
+                url
+            )));
+        }
+        _ => {
+            return Err(FetchError::Fatal(launcher_extension_api::anyhow!("Could not download this file: {}", url)));
+        }
+    };
+    io::copy(
+        &mut ProgressReader {
+            inner: resp,
+            progress,
+            counted,
+        },
+        &mut file,
+    )?;
     Ok(())
 }
 
-pub fn download_files_concurrent<P: AsRef<Path>>(download: &[(String, P)]) -> Result<()> {
-    let workers: usize = 4;
-    let chunks = download.chunks(workers);
-    let mut threads: Vec<JoinHandle<Result<()>>> = Vec::new();
-    for chunk in chunks {
-        let chunk: Vec<(String, PathBuf)> = chunk
-            .iter()
-            .map(|v| (v.0.to_string(), v.1.as_ref().to_path_buf()))
-            .collect();
-        threads.push(thread::spawn(move || {
-            for file in chunk {
-                download_file(&file.0, &file.1)?;
-            }
-            Ok(())
-        }));
+struct ProgressReader<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+    counted: &'a mut u64,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.add_bytes(read as u64);
+        *self.counted += read as u64;
+        Ok(read)
     }
-    for thread in threads {
-        thread.join().map_err(|_| launcher_extension_api::anyhow!("Can't join download thread"))??
+}
+
+fn verify_file(download: &Download, part: &Path, expected: &str, actual: &str) -> Result<()> {
+    if actual != expected {
+        remove_file(part)?;
+        return Err(launcher_extension_api::anyhow!(
+            "Checksum mismatch for {} (downloaded from {}): expected {}, got {}",
+            download.path.display(),
+            download.url,
+            expected,
+            actual
+        ));
     }
     Ok(())
 }
+
+/// Maven repositories publish `<artifact>.sha1` next to every artifact, use it for files
+/// the metadata gives no hash for.
+fn remote_sha1(client: &Client, url: &str) -> Option<String> {
+    let resp = client.get(format!("{}.sha1", url)).send().ok()?;
+    if resp.status() != StatusCode::OK {
+        return None;
+    }
+    let text = resp.text().ok()?;
+    text.split_whitespace()
+        .next()
+        .filter(|v| v.len() == 40)
+        .map(|v| v.to_lowercase())
+}
@@ -1,85 +1,225 @@
-use crate::download::{download_file, download_files_concurrent, download_files_single};
+use crate::arguments;
+use crate::artifact::Artifact;
+use crate::download::{Download, DownloadStats, Downloader};
+use crate::progress::Phase;
+use crate::config::OutputFolders;
 use crate::minecraft::forge::LibraryType;
-use crate::minecraft::version::Libraries;
+use crate::minecraft::liteloader::LiteLoader;
+use crate::modpack::{ModPack, Overrides};
+use crate::mods::{optional_path, profile_optional, ModsFolder};
+use crate::minecraft::version::{Libraries, NameLibrary};
 use crate::minecraft::GameType;
-use crate::minecraft::GameType::{Fabric, Forge};
-use crate::util::{generate_download_url, generate_lib_path, get_yarn_path, get_yarn_url, jar_url};
-use launcher_extension_api::Result;
+use crate::minecraft::forge::ForgeManifest;
+use crate::minecraft::neoforge::NeoForgeManifest;
+use crate::minecraft::libraries::{Arguments, Library};
+use crate::minecraft::GameType::{Fabric, Forge, ForgeInstaller, NeoForge, Quilt};
+use crate::natives::{self, NativeJar, NativePlatform};
+use crate::processors::InstallerPlan;
+use crate::rules::{self, RuleOutcome};
+use crate::util::{artifact_key, generate_download_url, mirror_url, generate_lib_path, get_yarn_path, get_yarn_url, jar_url, sorted_json};
+use launcher_extension_api::{Context, Result};
 use launcher_extension_api::launcher::profile::Profile;
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashSet, HashMap};
 use std::fs::{create_dir_all, remove_dir_all, File};
-use std::io;
-use std::iter::FromIterator;
-use std::path::PathBuf;
-use walkdir::WalkDir;
-use zip::ZipArchive;
-use launcher_extension_api::launcher::optional::{Optional, Action, FileAction, Location, OptionalFiles, Rule as LauncherRule, OsRule, CompareMode};
+use std::path::{Path, PathBuf};
+use launcher_extension_api::launcher::optional::{Optional, Action, FileAction, Location, OptionalFiles};
 use regex::Regex;
-use launcher_extension_api::launcher::validation::OsType;
 use path_slash::PathBufExt;
 
-pub fn generate_profile(
-    name: &str,
-    version: &str,
+pub struct ProfileOptions<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub address: &'a str,
+    pub port: u32,
+    pub assets: Option<&'a str>,
+    pub java: &'a Path,
+    pub mirrors: &'a BTreeMap<String, String>,
+    /// LaunchWrapper tweak classes added after the ones of the loader.
+    pub tweak_classes: &'a [String],
+    pub liteloader: Option<&'a LiteLoader>,
+    pub pack: Option<&'a ModPack>,
+    pub mods: Option<&'a ModsFolder>,
+}
+
+const VANILLA_MAIN_CLASS: &str = "net.minecraft.client.main.Main";
+const LAUNCHWRAPPER_MAIN_CLASS: &str = "net.minecraft.launchwrapper.Launch";
+const LAUNCHWRAPPER_LIBRARIES: [&str; 2] = ["net.minecraft:launchwrapper:1.12", "org.ow2.asm:asm-all:5.2"];
+
+/// Profile libraries in classpath order. Loader libraries go first so they take precedence,
+/// vanilla libraries replaced by a loader artifact of another version are dropped.
+#[derive(Default)]
+struct LibraryOrder {
+    loader: Vec<(String, String)>,
+    vanilla: Vec<(String, String)>,
+    /// Maven path of every library to its path in the libraries folder.
+    maven_paths: HashMap<String, String>,
+}
+
+impl LibraryOrder {
+    fn add_loader(&mut self, name: &str, path: String) {
+        self.add_maven_path(name, &path);
+        self.loader.push((artifact_key(name), path));
+    }
+
+    fn add_vanilla(&mut self, name: &str, path: String) {
+        self.add_maven_path(name, &path);
+        self.vanilla.push((artifact_key(name), path));
+    }
+
+    fn add_maven_path(&mut self, name: &str, path: &str) {
+        if let Ok(artifact) = name.parse::<Artifact>() {
+            self.maven_paths.insert(artifact.to_path().to_slash_lossy(), path.to_string());
+        }
+    }
+
+    /// Whether a library of the same artifact is already part of the profile.
+    fn contains(&self, name: &str) -> bool {
+        let key = artifact_key(name);
+        self.loader.iter().chain(&self.vanilla).any(|(known, _)| *known == key)
+    }
+
+    fn into_paths(self) -> Vec<String> {
+        let loader_keys: HashSet<String> = self.loader.iter().map(|(key, _)| key.clone()).collect();
+        let vanilla = self
+            .vanilla
+            .into_iter()
+            .filter(|(key, _)| !loader_keys.contains(key));
+        let mut known_paths = HashSet::new();
+        self.loader
+            .into_iter()
+            .chain(vanilla)
+            .map(|(_, path)| path)
+            .filter(|path| known_paths.insert(path.clone()))
+            .collect()
+    }
+}
+
+/// Everything a profile generation would write: files to download and the resulting
+/// profile and optionals. Building a plan only fetches metadata.
+pub struct ProfilePlan {
+    downloads: Vec<(Phase, Vec<Download>)>,
+    folders: Vec<PathBuf>,
+    native_folder: PathBuf,
+    native_jars: Vec<NativeJar>,
+    temp_natives: PathBuf,
+    installer: Option<InstallerPlan>,
+    overrides: Vec<Overrides>,
+    profile_folder: PathBuf,
+    profile: Profile,
+    optionals: Vec<Optional>,
+}
+
+pub fn plan_profile(
+    options: &ProfileOptions,
     manifest: Libraries,
-    address: &str,
-    port: u32,
     game_type: GameType,
-    assets: Option<&str>,
-) -> Result<()> {
-    let base = PathBuf::from("static");
-    let native_folder = &base.join("natives").join(version);
-    let assets_folder = &base.join("assets").join(assets.unwrap_or(name));
-    let profile_folder = &base.join("profiles").join(name);
-    let libraries_folder = base.join("libraries");
+    folders: &OutputFolders,
+) -> Result<ProfilePlan> {
+    let ProfileOptions {
+        name,
+        version,
+        address,
+        port,
+        assets,
+        java,
+        mirrors,
+        tweak_classes: extra_tweak_classes,
+        liteloader,
+        pack,
+        mods,
+    } = *options;
+    let native_folder = &folders.natives.join(version);
+    let assets_folder = &folders.assets.join(assets.unwrap_or(name));
+    let profile_folder = &folders.profiles.join(name);
+    let libraries_folder = folders.libraries.clone();
     let mut client_args = Vec::new();
-    let mut main_class = "net/minecraft/client/main/Main".to_string();
+    let mut tweak_classes = Vec::new();
+    // Versions before 1.6 and alpha/beta start through LaunchWrapper or the applet
+    // class, their main class is kept even for vanilla profiles.
+    let mut main_class = manifest
+        .main_class
+        .clone()
+        .filter(|main_class| manifest.custom || main_class != VANILLA_MAIN_CLASS)
+        .unwrap_or_else(|| VANILLA_MAIN_CLASS.replace(".", "/"));
+    let mut jvm_args = Vec::new();
     let mut classpath = Vec::new();
     let mut optionals: Vec<Optional> = Vec::new();
-    std::fs::create_dir_all(&native_folder)?;
-    std::fs::create_dir_all(&assets_folder)?;
-    std::fs::create_dir_all(&profile_folder)?;
-    std::fs::create_dir_all(&libraries_folder)?;
+    let mut downloads = Vec::new();
+    let mut installer_plan = None;
+    let mut loader_arguments = None;
+    let mut virtual_assets = matches!(manifest.asset_index.id.as_str(), "legacy" | "pre-1.6");
     if assets.is_none() {
-        println!("Download assets...");
-        let assets = crate::util::get_assets(&manifest.asset_index.url)?;
+        let assets = crate::util::get_assets(&mirror_url(&manifest.asset_index.url, mirrors))?;
         let objects_path = assets_folder.join("objects");
         let mut assets_download = Vec::new();
-        for (_, object) in assets.objects {
-            let path = objects_path.join(&object.hash[0..2]);
-            assets_download.push((
-                format!(
-                    "https://resources.download.minecraft.net/{}/{}",
-                    &object.hash[0..2],
-                    object.hash
-                ),
+        let mut known_hashes = HashSet::new();
+        virtual_assets = assets.is_virtual;
+        for (asset_name, object) in assets.objects {
+            let url = format!(
+                "https://resources.download.minecraft.net/{}/{}",
+                &object.hash[0..2],
+                object.hash
+            );
+            // Versions before 1.7.3 read assets by name, from `virtual/<index>` in the
+            // assets folder or from `resources` in the game folder.
+            if assets.is_virtual || assets.map_to_resources {
+                let path = if assets.map_to_resources {
+                    profile_folder.join("resources").join(&asset_name)
+                } else {
+                    virtual_assets_folder(assets_folder, &manifest.asset_index.id).join(&asset_name)
+                };
+                assets_download.push(Download::new(&url, path, Some(&object.hash)).with_size(Some(object.size as u64)));
+                continue;
+            }
+            if !known_hashes.insert(object.hash.clone()) {
+                continue;
+            }
+            let path = objects_path.join(&object.hash[0..2]).join(&object.hash);
+            assets_download.push(Download::new(
+                &url,
                 path,
-            ));
+                Some(&object.hash),
+            ).with_size(Some(object.size as u64)));
         }
-        download_files_single(&assets_download)?;
-        download_file(
+        assets_download.push(Download::to_folder(
             &manifest.asset_index.url,
-            assets_folder.join("indexes").to_str().unwrap(),
-        )?;
+            assets_folder.join("indexes"),
+            Some(&manifest.asset_index.sha1),
+        )?.with_size(manifest.asset_index.size));
+        downloads.push((Phase::Assets, assets_download));
+    }
+    let client = manifest.downloads.client.as_ref().context("Can't find client download")?;
+    let client_download = Download::new(
+        &client.url,
+        profile_folder.join("minecraft.jar"),
+        client.sha1.as_deref(),
+    ).with_size(client.size);
+    let mut client_downloads = vec![client_download];
+    let logging = manifest
+        .logging
+        .as_ref()
+        .filter(|_| manifest.custom)
+        .and_then(|logging| logging.client.as_ref());
+    if let Some(logging) = logging {
+        client_downloads.push(Download::new(
+            &logging.file.url,
+            profile_folder.join(&logging.file.id),
+            Some(&logging.file.sha1),
+        ).with_size(logging.file.size));
+        jvm_args.push(logging.argument.replace("${path}", &logging.file.id));
     }
-    println!("Download client...");
-    download_file(
-        &manifest.downloads.client.unwrap().url,
-        &profile_folder.to_str().unwrap(),
-    )?;
-    std::fs::rename(
-        profile_folder.join("client.jar").as_path(),
-        profile_folder.join("minecraft.jar").as_path(),
-    )?;
+    downloads.push((Phase::Client, client_downloads));
     classpath.push("minecraft.jar".to_string());
-    println!("Download libs...");
     let version_regex = Regex::new(r"-\d.\d.\d.+").unwrap();
-    let mut profile_lib_paths = HashSet::new();
+    let mut profile_lib_paths = LibraryOrder::default();
     let libs = &manifest
         .libraries;
     let mut download_list = Vec::with_capacity(libs.len());
     for lib in libs {
-        if let Some(file) = &lib.downloads.artifact {
+        if natives::is_native_library(&lib.name) {
+            continue;
+        }
+        if let Some(file) = &lib.artifact() {
             let lib_path = PathBuf::from(
                 file
                     .path
@@ -89,54 +229,48 @@ pub fn generate_profile(
                     .last()
                     .unwrap(),
             );
-            if let Some(rules) = &lib.rules {
-                for rule in rules {
+            let outcome = match &lib.rules {
+                Some(rules) => rules::evaluate(rules, &HashMap::new()),
+                None => RuleOutcome::Always,
+            };
+            match outcome {
+                RuleOutcome::Always => {
+                    profile_lib_paths.add_vanilla(&lib.name, lib_path.to_str().unwrap().to_string());
+                }
+                RuleOutcome::Never => continue,
+                RuleOutcome::Only(optional_rules) => {
                     let file_name = version_regex.replace_all(lib_path.file_name().unwrap().to_str().unwrap(), ".jar");
                     let new_lib_path = lib_path.with_file_name(&file_name.to_string());
-                    let rename_list = {
-                        let mut map = HashMap::new();
-                        map.insert(lib_path.clone(), PathBuf::from(new_lib_path.to_slash_lossy()));
-                        map
-                    };
-                    profile_lib_paths.insert(new_lib_path.to_slash_lossy());
-                    let action = Action::Files(FileAction {
-                        location: Location::Libraries,
-                        files: OptionalFiles {
-                            original_paths: vec![],
-                            rename_paths: rename_list,
-                        },
-                    });
-                    let optional_rule = if rule.action.eq("allow") && rule.os.is_none() {
-                        LauncherRule::OsType(OsRule {
-                            os_type: OsType::MacOsX64,
-                            compare_mode: CompareMode::Unequal,
-                        })
-                    } else if rule.action.eq("allow") && rule.os.as_ref().map(|os| &os.name).map(|name| name.eq("osx")).unwrap_or(false) {
-                        LauncherRule::OsType(OsRule {
-                            os_type: OsType::MacOsX64,
-                            compare_mode: CompareMode::Equal,
-                        })
-                    } else {
-                        continue;
-                    };
-                    let optional = Optional {
-                        actions: vec![action],
-                        rules: vec![optional_rule],
-                        enabled: true,
-                        visible: false,
-                        description: None,
-                        name: None,
-                    };
-                    optionals.push(optional);
+                    profile_lib_paths.add_vanilla(&lib.name, new_lib_path.to_slash_lossy());
+                    for optional_rule in optional_rules {
+                        let rename_list = {
+                            let mut map = HashMap::new();
+                            map.insert(lib_path.clone(), PathBuf::from(new_lib_path.to_slash_lossy()));
+                            map
+                        };
+                        let action = Action::Files(FileAction {
+                            location: Location::Libraries,
+                            files: OptionalFiles {
+                                original_paths: vec![],
+                                rename_paths: rename_list,
+                            },
+                        });
+                        let optional = Optional {
+                            actions: vec![action],
+                            rules: vec![optional_rule],
+                            enabled: true,
+                            visible: false,
+                            description: None,
+                            name: None,
+                        };
+                        optionals.push(optional);
+                    }
                 }
-            } else {
-                profile_lib_paths.insert(lib_path.to_str().unwrap().to_string());
             }
-            let url = lib.downloads.artifact.as_ref().unwrap().url.to_string();
-            download_list.push((url, libraries_folder.clone()));
+            download_list.push(Download::to_folder(&file.url, &libraries_folder, file.sha1.as_deref())?.with_size(file.size));
         }
     }
-    download_files_concurrent(&download_list)?;
+    downloads.push((Phase::Libraries, download_list));
     match game_type {
         Fabric(mut fabric_manifest) => {
             fabric_manifest
@@ -148,180 +282,451 @@ pub fn generate_profile(
                 let mut lib_path = PathBuf::from(generate_lib_path(&v.name).split("/")
                     .last()
                     .unwrap());
-                profile_lib_paths.insert(lib_path.to_str().unwrap().to_string());
+                profile_lib_paths.add_loader(&v.name, lib_path.to_str().unwrap().to_string());
                 lib_path.pop();
                 let url = generate_download_url(&v.url, &v.name);
-                download_list.push((url, libraries_folder.clone()));
+                download_list.push(Download::to_folder(&url, &libraries_folder, None)?);
             }
-            download_files_concurrent(&download_list)?;
             let mappings_url = get_yarn_url(version);
             let mut lib_path = PathBuf::from(get_yarn_path(&version));
-            profile_lib_paths.insert(lib_path.to_str().unwrap().to_string());
+            profile_lib_paths.add_loader(
+                &format!("net.fabricmc:intermediary:{}", version),
+                lib_path.to_str().unwrap().to_string(),
+            );
             lib_path.pop();
-            download_file(&mappings_url, libraries_folder.clone())?;
+            download_list.push(Download::to_folder(&mappings_url, &libraries_folder, None)?);
+            downloads.push((Phase::Loader, download_list));
             main_class = fabric_manifest.main_class.client;
         }
+        Quilt(mut quilt_manifest) => {
+            let libraries = quilt_manifest.client_libraries(version);
+            let mut download_list = Vec::with_capacity(libraries.len());
+            for v in &libraries {
+                download_list.push(plan_name_library(v, &libraries_folder, &mut profile_lib_paths)?);
+            }
+            downloads.push((Phase::Loader, download_list));
+            main_class = quilt_manifest.main_class.client;
+        }
         Forge(forge_manifest) => {
-            main_class = forge_manifest.main_class;
-            let mut download_list = Vec::with_capacity(forge_manifest.libraries.len());
-            for library in &forge_manifest.libraries {
-                match library {
-                    LibraryType::PathLibrary(v) => {
-                        if v.downloads.artifact.as_ref().unwrap().path.is_some() {
-                            let lib_path = PathBuf::from(
-                                v.downloads
-                                    .artifact
-                                    .as_ref()
-                                    .unwrap()
-                                    .path
-                                    .as_ref()
-                                    .unwrap()
-                                    .split("/")
-                                    .last()
-                                    .unwrap()
-                                    .to_string(),
-                            );
-                            profile_lib_paths.insert(lib_path.to_str().unwrap().to_string());
-                            let url = v.downloads.artifact.as_ref().unwrap().url.to_string();
-                            download_list.push((url, libraries_folder.clone()));
-                        }
-                    }
-                    LibraryType::NameLibrary(v) => {
-                        let mut lib_path = PathBuf::from(generate_lib_path(&v.name)
-                            .split("/")
-                            .last()
-                            .unwrap()
-                            .to_string());
-                        profile_lib_paths.insert(lib_path.to_str().unwrap().to_string());
-                        lib_path.pop();
-                        let url = generate_download_url(&v.url, &v.name);
-                        download_list.push((url, libraries_folder.clone()));
-                    }
-                }
+            let (forge_main_class, download_list) =
+                plan_forge(forge_manifest, &libraries_folder, &mut profile_lib_paths, &mut tweak_classes)?;
+            if !download_list.is_empty() {
+                downloads.push((Phase::Loader, download_list));
             }
-            download_files_concurrent(&download_list)?;
-            if let Some(files) = forge_manifest.maven_files {
-                let mut download_list = Vec::with_capacity(files.len());
-                for v in files {
-                    if v.downloads.artifact.as_ref().unwrap().path.is_some() {
-                        let mut lib_path = PathBuf::from(
-                            v.downloads
-                                .artifact
-                                .as_ref()
-                                .unwrap()
-                                .path
-                                .as_ref()
-                                .unwrap()
-                                .to_string(),
-                        );
-                        lib_path.pop();
-                        let url = v.downloads.artifact.as_ref().unwrap().url.to_string();
-                        let path = libraries_folder.join(lib_path);
-                        download_list.push((url, path));
-                    }
+            main_class = forge_main_class;
+        }
+        ForgeInstaller(mut installer) | NeoForge(NeoForgeManifest { mut installer }) => {
+            let work_folder = folders.root.join("forge_temp");
+            let work_libraries = InstallerPlan::work_libraries(&work_folder);
+            let mut download_list = Vec::new();
+            let mut bundled = Vec::new();
+            let mut generated = Vec::new();
+            for library in &installer.version.libraries {
+                let path = plan_installer_library(
+                    library,
+                    &installer,
+                    &libraries_folder,
+                    &mut download_list,
+                    &mut bundled,
+                    &mut generated,
+                )?;
+                if let Some(path) = path {
+                    profile_lib_paths.add_loader(&library.name, path);
                 }
-                download_files_concurrent(&download_list)?;
             }
-            if let Some(tweakers) = forge_manifest.tweakers {
-                for tweak in tweakers {
-                    client_args.push("--tweakClass".to_string());
-                    client_args.push(tweak);
-                }
+            for library in &installer.profile.libraries {
+                plan_installer_library(
+                    library,
+                    &installer,
+                    &work_libraries,
+                    &mut download_list,
+                    &mut bundled,
+                    &mut generated,
+                )?;
             }
+            loader_arguments = installer.version.arguments.take();
+            downloads.push((Phase::Loader, download_list));
+            main_class = installer.version.main_class.clone();
+            installer_plan = Some(InstallerPlan::new(
+                installer,
+                java.to_path_buf(),
+                work_folder,
+                profile_folder.join("minecraft.jar"),
+                libraries_folder.clone(),
+                bundled,
+                generated,
+            ));
         }
         _ => {}
     }
-    println!("Download natives...");
-    let temp_natives = base.join("natives_temp");
-    create_dir_all(&temp_natives)?;
-    let natives = manifest
-        .libraries
-        .iter()
-        .filter(|v| v.downloads.classifiers.is_some())
-        .flat_map(|v| {
-            let mut natives: Vec<(String, PathBuf)> = Vec::new();
-            if let Some(f) = v
-                .downloads
-                .classifiers
-                .as_ref()
-                .unwrap()
-                .natives_osx
-                .as_ref()
-            {
-                natives.push(jar_url(&temp_natives, f));
+    if let Some(liteloader) = liteloader {
+        let mut libraries = liteloader.artefact.libraries.clone();
+        libraries.push(NameLibrary {
+            name: liteloader.name(),
+            url: liteloader.repo_url.clone(),
+        });
+        let mut download_list = Vec::with_capacity(libraries.len());
+        for v in &libraries {
+            download_list.push(plan_name_library(v, &libraries_folder, &mut profile_lib_paths)?);
+        }
+        downloads.push((Phase::Loader, download_list));
+        tweak_classes.push(liteloader.artefact.tweak_class.clone());
+    }
+    tweak_classes.extend(extra_tweak_classes.iter().cloned());
+    if !tweak_classes.is_empty() {
+        let mut download_list = Vec::new();
+        for name in LAUNCHWRAPPER_LIBRARIES.iter() {
+            if !profile_lib_paths.contains(name) {
+                let library = NameLibrary {
+                    name: name.to_string(),
+                    url: "https://libraries.minecraft.net/".to_string(),
+                };
+                download_list.push(plan_name_library(&library, &libraries_folder, &mut profile_lib_paths)?);
             }
-            if let Some(f) = v
-                .downloads
-                .classifiers
-                .as_ref()
-                .unwrap()
-                .natives_windows
-                .as_ref()
-            {
-                natives.push(jar_url(&temp_natives, f));
+        }
+        if !download_list.is_empty() {
+            downloads.push((Phase::Loader, download_list));
+        }
+        main_class = LAUNCHWRAPPER_MAIN_CLASS.to_string();
+        let mut known_tweak_classes = HashSet::new();
+        for tweak in tweak_classes {
+            if known_tweak_classes.insert(tweak.clone()) {
+                client_args.push("--tweakClass".to_string());
+                client_args.push(tweak);
             }
-            if let Some(f) = v
-                .downloads
-                .classifiers
-                .as_ref()
-                .unwrap()
-                .natives_linux
-                .as_ref()
-            {
-                natives.push(jar_url(&temp_natives, f));
+        }
+    }
+    let mut overrides = Vec::new();
+    if let Some(pack) = pack {
+        let download_list = pack
+            .files
+            .iter()
+            .map(|file| {
+                let path = match file.optional {
+                    Some(_) => optional_path(&file.path),
+                    None => file.path.clone(),
+                };
+                Download::new(&file.url, profile_folder.join(path), file.sha1.as_deref())
+                    .with_sha512(file.sha512.as_deref())
+                    .with_size(file.size)
+                    .with_fallback_urls(&file.fallback_urls)
+            })
+            .collect();
+        downloads.push((Phase::Mods, download_list));
+        for file in &pack.files {
+            if let Some(name) = &file.optional {
+                optionals.push(profile_optional(&file.path, name, None, false));
             }
-            natives
-        })
-        .collect::<Vec<_>>();
-    download_files_concurrent(&natives)?;
-    for entry in WalkDir::new(&temp_natives)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_file())
-    {
-        if let Ok(mut zip) = ZipArchive::new(File::open(entry.path()).unwrap()) {
-            for index in 0..zip.len() {
-                let mut file = zip.by_index(index).unwrap();
-                if file.is_file() {
-                    if file.name().ends_with(".so")
-                        || file.name().ends_with(".dll")
-                        || file.name().ends_with(".dylib")
-                    {
-                        if let Ok(mut outfile) =
-                        File::create(native_folder.join(&file.mangled_name()))
-                        {
-                            io::copy(&mut file, &mut outfile)?;
-                        }
-                    }
+        }
+        overrides = pack.overrides.clone();
+    }
+    if let Some(mods) = mods {
+        overrides.push(mods.overrides());
+        optionals.extend(mods.optionals());
+    }
+    let vanilla_arguments = match (manifest.arguments, &manifest.minecraft_arguments) {
+        (Some(arguments), _) => arguments,
+        (None, Some(legacy)) => Arguments::from_legacy(legacy),
+        (None, None) => Arguments::default(),
+    };
+    let loader_arguments = loader_arguments.unwrap_or_default();
+    let arguments = arguments::resolve(
+        &vanilla_arguments.jvm.iter().chain(&loader_arguments.jvm).collect::<Vec<_>>(),
+        &vanilla_arguments.game.iter().chain(&loader_arguments.game).collect::<Vec<_>>(),
+        &profile_lib_paths.maven_paths,
+    );
+    optionals.extend(arguments.optionals);
+    jvm_args.extend(arguments.jvm);
+    if let Some(pack) = pack {
+        jvm_args.extend(pack.jvm_args.iter().cloned());
+    }
+    let mut profile_client_args = arguments.game;
+    profile_client_args.append(&mut client_args);
+    let temp_natives = folders.root.join("natives_temp");
+    let mut natives = Vec::new();
+    let mut native_jars = Vec::new();
+    for lib in &manifest.libraries {
+        let exclude = lib
+            .extract
+            .as_ref()
+            .map(|extract| extract.exclude.clone())
+            .unwrap_or_default();
+        if lib.downloads.classifiers.is_some() {
+            for (file, platforms) in natives::classifier_natives(lib) {
+                let download = jar_url(&temp_natives, file)?;
+                native_jars.push(NativeJar {
+                    path: download.path.clone(),
+                    platforms,
+                    exclude: exclude.clone(),
+                });
+                natives.push(download);
+            }
+        } else if let Some(file) = &lib.downloads.artifact {
+            let platforms = natives::library_native_platforms(&lib.name, lib.rules.as_deref());
+            if !platforms.is_empty() {
+                let download = jar_url(&temp_natives, file)?;
+                native_jars.push(NativeJar {
+                    path: download.path.clone(),
+                    platforms,
+                    exclude,
+                });
+                natives.push(download);
+            }
+        }
+    }
+    downloads.push((Phase::Natives, natives));
+    let game_assets_folder = if virtual_assets {
+        virtual_assets_folder(assets_folder, &manifest.asset_index.id)
+    } else {
+        assets_folder.clone()
+    };
+    let profile = Profile {
+        name: name.to_string(),
+        version: version.to_string(),
+        libraries: profile_lib_paths.into_paths(),
+        class_path: classpath,
+        main_class: main_class.replace(".", "/"),
+        update_verify: vec![],
+        update_exclusion: vec![],
+        jvm_args,
+        client_args: profile_client_args,
+        assets: manifest.asset_index.id,
+        assets_dir: game_assets_folder
+            .strip_prefix(&folders.root)
+            .unwrap_or(&game_assets_folder)
+            .to_path_buf()
+            .to_slash_lossy(),
+        server_name: address.to_string(),
+        server_port: port,
+    };
+    for (_, downloads) in &mut downloads {
+        for download in downloads {
+            download.url = mirror_url(&download.url, mirrors);
+            for url in &mut download.fallback_urls {
+                *url = mirror_url(url, mirrors);
+            }
+        }
+    }
+    Ok(ProfilePlan {
+        downloads,
+        folders: vec![
+            native_folder.clone(),
+            assets_folder.clone(),
+            profile_folder.clone(),
+            libraries_folder,
+            temp_natives.clone(),
+        ],
+        native_folder: native_folder.clone(),
+        native_jars,
+        temp_natives,
+        installer: installer_plan,
+        overrides,
+        profile_folder: profile_folder.clone(),
+        profile,
+        optionals,
+    })
+}
+
+/// Assets of virtual indexes by name, the folder the game gets as `--assetsDir`.
+fn virtual_assets_folder(assets_folder: &Path, index_id: &str) -> PathBuf {
+    assets_folder.join("virtual").join(index_id)
+}
+
+/// Loader library given by maven name, downloaded flat into the libraries folder.
+fn plan_name_library(
+    library: &NameLibrary,
+    libraries_folder: &Path,
+    profile_lib_paths: &mut LibraryOrder,
+) -> Result<Download> {
+    let lib_path = generate_lib_path(&library.name);
+    let file_name = lib_path.rsplit('/').next().unwrap();
+    profile_lib_paths.add_loader(&library.name, file_name.to_string());
+    let url = generate_download_url(&library.url, &library.name);
+    Download::to_folder(&url, libraries_folder, None)
+}
+
+/// Loader libraries of MultiMC style Forge manifests.
+fn plan_forge(
+    forge_manifest: ForgeManifest,
+    libraries_folder: &Path,
+    profile_lib_paths: &mut LibraryOrder,
+    tweak_classes: &mut Vec<String>,
+) -> Result<(String, Vec<Download>)> {
+    let mut download_list = Vec::with_capacity(forge_manifest.libraries.len());
+    for library in &forge_manifest.libraries {
+        match library {
+            LibraryType::PathLibrary(v) => {
+                if v.downloads.artifact.as_ref().unwrap().path.is_some() {
+                    let lib_path = PathBuf::from(
+                        v.downloads
+                            .artifact
+                            .as_ref()
+                            .unwrap()
+                            .path
+                            .as_ref()
+                            .unwrap()
+                            .split("/")
+                            .last()
+                            .unwrap()
+                            .to_string(),
+                    );
+                    profile_lib_paths.add_loader(&v.name, lib_path.to_str().unwrap().to_string());
+                    let artifact = v.downloads.artifact.as_ref().unwrap();
+                    download_list.push(Download::to_folder(&artifact.url, libraries_folder, artifact.sha1.as_deref())?.with_size(artifact.size));
                 }
             }
+            LibraryType::NameLibrary(v) => {
+                let mut lib_path = PathBuf::from(generate_lib_path(&v.name)
+                    .split("/")
+                    .last()
+                    .unwrap()
+                    .to_string());
+                profile_lib_paths.add_loader(&v.name, lib_path.to_str().unwrap().to_string());
+                lib_path.pop();
+                let url = generate_download_url(&v.url, &v.name);
+                download_list.push(Download::to_folder(&url, libraries_folder, None)?);
+            }
+        }
+    }
+    if let Some(files) = forge_manifest.maven_files {
+        for v in files {
+            if v.downloads.artifact.as_ref().unwrap().path.is_some() {
+                let mut lib_path = PathBuf::from(
+                    v.downloads
+                        .artifact
+                        .as_ref()
+                        .unwrap()
+                        .path
+                        .as_ref()
+                        .unwrap()
+                        .to_string(),
+                );
+                lib_path.pop();
+                let artifact = v.downloads.artifact.as_ref().unwrap();
+                let path = libraries_folder.join(lib_path);
+                download_list.push(Download::to_folder(&artifact.url, path, artifact.sha1.as_deref())?.with_size(artifact.size));
+            }
         }
     }
-    remove_dir_all(temp_natives)?;
-    println!("Generate json profile...");
-    serde_json::to_writer_pretty(
-        File::create(profile_folder.join(format!("profile.json"))).unwrap(),
-        &Profile {
-            name: name.to_string(),
-            version: version.to_string(),
-            libraries: Vec::from_iter(profile_lib_paths),
-            class_path: classpath,
-            main_class: main_class.replace(".", "/"),
-            update_verify: vec![],
-            update_exclusion: vec![],
-            jvm_args: vec![],
-            client_args,
-            assets: manifest.asset_index.id,
-            assets_dir: format!("assets/{}", assets.unwrap_or(name)),
-            server_name: address.to_string(),
-            server_port: port,
-        },
-    )?;
-    println!("Generate optionals...");
-    serde_json::to_writer_pretty(
-        File::create(profile_folder.join(format!("optionals.json"))).unwrap(),
-        &optionals,
-    )?;
-    Ok(())
+    if let Some(tweakers) = forge_manifest.tweakers {
+        tweak_classes.extend(tweakers);
+    }
+    Ok((forge_manifest.main_class, download_list))
 }
 
+/// Tweak classes and LiteLoader replace the main class with LaunchWrapper, which only
+/// starts vanilla and the Forge versions that already run through it.
+pub fn supports_launchwrapper(game_type: &GameType, manifest: &Libraries) -> bool {
+    let main_class = match game_type {
+        GameType::Vanilla if !manifest.custom => return true,
+        GameType::Vanilla => manifest.main_class.as_deref().unwrap_or(VANILLA_MAIN_CLASS),
+        GameType::Forge(forge_manifest) => &forge_manifest.main_class,
+        _ => return false,
+    };
+    main_class == VANILLA_MAIN_CLASS || main_class == LAUNCHWRAPPER_MAIN_CLASS
+}
+
+/// Installer libraries keep the maven layout, Forge locates its patched jars relative
+/// to them. Artifacts without an url are either shipped inside the installer or created
+/// by its processors, like the patched client jar.
+fn plan_installer_library(
+    library: &Library,
+    installer: &crate::minecraft::installer::ForgeInstaller,
+    folder: &Path,
+    download_list: &mut Vec<Download>,
+    bundled: &mut Vec<(String, PathBuf)>,
+    generated: &mut Vec<PathBuf>,
+) -> Result<Option<String>> {
+    let artifact = match &library.downloads.artifact {
+        Some(artifact) => artifact,
+        None => return Ok(None),
+    };
+    let lib_path = match &artifact.path {
+        Some(path) => path.clone(),
+        None => generate_lib_path(&library.name),
+    };
+    let path = folder.join(&lib_path);
+    if artifact.url.is_empty() {
+        if installer.has_file(&format!("maven/{}", lib_path)) {
+            bundled.push((lib_path.clone(), path));
+        } else {
+            generated.push(path);
+        }
+    } else {
+        download_list.push(Download::new(&artifact.url, path, artifact.sha1.as_deref()).with_size(artifact.size));
+    }
+    Ok(Some(lib_path))
+}
+
+impl ProfilePlan {
+    /// Prints every file the generation would write without touching the disk.
+    pub fn print(&self) -> Result<()> {
+        for (phase, downloads) in &self.downloads {
+            println!("[{}] {} files", phase, downloads.len());
+            for download in downloads {
+                let size = download.size.map(|v| v.to_string()).unwrap_or_else(|| "unknown".to_string());
+                println!("  {} -> {} ({} bytes)", download.url, download.path.display(), size);
+            }
+        }
+        println!("{}:", self.profile_folder.join("profile.json").display());
+        println!("{}", serde_json::to_string_pretty(&self.profile)?);
+        println!("{}:", self.profile_folder.join("optionals.json").display());
+        println!("{}", serde_json::to_string_pretty(&sorted_json(serde_json::to_value(&self.optionals)?))?);
+        let platforms: BTreeSet<NativePlatform> = self
+            .native_jars
+            .iter()
+            .flat_map(|jar| jar.platforms.iter().copied())
+            .collect();
+        if !platforms.is_empty() {
+            println!(
+                "The optionals above are partial, extracting {} natives jars adds one optional per platform:",
+                self.native_jars.len()
+            );
+            for platform in platforms {
+                println!("  {} ({})", platform.folder(), self.native_folder.join(platform.folder()).display());
+            }
+        }
+        for overrides in &self.overrides {
+            println!("Overrides {} are copied into {}", overrides.describe(), self.profile_folder.display());
+        }
+        if let Some(installer) = &self.installer {
+            installer.print()?;
+        }
+        Ok(())
+    }
+
+    pub fn execute(mut self, downloader: &Downloader) -> Result<()> {
+        for folder in &self.folders {
+            create_dir_all(folder)?;
+        }
+        let mut stats = DownloadStats::default();
+        for (phase, downloads) in &self.downloads {
+            stats.merge(downloader.download_files(*phase, downloads)?);
+        }
+        if let Some(installer) = &self.installer {
+            installer.run(downloader.progress())?;
+        }
+        for overrides in &self.overrides {
+            let copied = overrides.apply(&self.profile_folder)?;
+            downloader
+                .progress()
+                .message(&format!("Copied {} files from {}", copied, overrides.describe()));
+        }
+        let native_optionals = natives::extract_natives(&self.native_jars, &self.native_folder)?;
+        self.optionals.extend(native_optionals);
+        remove_dir_all(&self.temp_natives)?;
+        let progress = downloader.progress();
+        progress.message(&format!("Downloaded {} files, reused {} files", stats.downloaded, stats.reused));
+        progress.message("Generate json profile...");
+        serde_json::to_writer_pretty(
+            File::create(self.profile_folder.join(format!("profile.json"))).unwrap(),
+            &self.profile,
+        )?;
+        progress.message("Generate optionals...");
+        serde_json::to_writer_pretty(
+            File::create(self.profile_folder.join(format!("optionals.json"))).unwrap(),
+            &sorted_json(serde_json::to_value(&self.optionals)?),
+        )?;
+        Ok(())
+    }
+}
@@ -2,13 +2,21 @@ use launcher_extension_api::LauncherExtension;
 use launcher_extension_api::command::{CommandRegister};
 use crate::command::ProfileGenerationCommand;
 
+mod arguments;
 mod command;
+mod config;
 mod artifact;
 mod download;
 mod minecraft;
+mod modpack;
+mod mods;
+mod natives;
 mod util;
 mod validator;
 mod generator;
+mod progress;
+mod rules;
+mod processors;
 
 #[no_mangle]
 pub extern "Rust" fn new_extension() -> (String, Box<dyn LauncherExtension>) {
@@ -1,14 +1,24 @@
 use crate::minecraft::fabric::FabricLoaderManifest;
 use crate::minecraft::forge::ForgeManifest;
+use crate::minecraft::installer::ForgeInstaller;
+use crate::minecraft::neoforge::NeoForgeManifest;
+use crate::minecraft::quilt::QuiltLoaderManifest;
 
 pub mod assets;
 pub mod fabric;
 pub mod forge;
+pub mod installer;
 pub mod libraries;
+pub mod liteloader;
+pub mod neoforge;
+pub mod quilt;
 pub mod version;
 
 pub enum GameType {
     Vanilla,
     Forge(ForgeManifest),
+    ForgeInstaller(ForgeInstaller),
+    NeoForge(NeoForgeManifest),
     Fabric(FabricLoaderManifest),
+    Quilt(QuiltLoaderManifest),
 }
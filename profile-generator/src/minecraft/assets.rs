@@ -4,6 +4,10 @@ use std::collections::HashMap;
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Assets {
     pub objects: HashMap<String, Object>,
+    #[serde(rename = "virtual", default)]
+    pub is_virtual: bool,
+    #[serde(default)]
+    pub map_to_resources: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -0,0 +1,119 @@
+use crate::minecraft::libraries::{Arguments, Library};
+use launcher_extension_api::{Context, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::str::FromStr;
+use zip::ZipArchive;
+
+/// Official Forge installer of 1.13+, read fully into memory. Its processors produce
+/// the patched client jars the profile needs.
+pub struct ForgeInstaller {
+    pub profile: InstallProfile,
+    pub version: InstallerVersion,
+    pub jar: Vec<u8>,
+}
+
+/// `install_profile.json` of the installer.
+#[derive(Deserialize, Serialize)]
+pub struct InstallProfile {
+    pub minecraft: String,
+    pub json: String,
+    #[serde(default)]
+    pub data: HashMap<String, SidedData>,
+    #[serde(default)]
+    pub processors: Vec<Processor>,
+    pub libraries: Vec<Library>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SidedData {
+    pub client: String,
+    pub server: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Processor {
+    pub jar: String,
+    #[serde(default)]
+    pub classpath: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub sides: Option<Vec<String>>,
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+}
+
+impl Processor {
+    pub fn is_client(&self) -> bool {
+        self.sides
+            .as_ref()
+            .map(|sides| sides.iter().any(|side| side == "client"))
+            .unwrap_or(true)
+    }
+}
+
+/// `version.json` of the installer, the runtime part of the Forge profile.
+#[derive(Deserialize, Serialize)]
+pub struct InstallerVersion {
+    #[serde(rename = "mainClass")]
+    pub main_class: String,
+    pub libraries: Vec<Library>,
+    pub arguments: Option<Arguments>,
+}
+
+impl ForgeInstaller {
+    pub fn download(url: &str) -> Result<Self> {
+        let resp = reqwest::blocking::get(url)?.error_for_status()?;
+        ForgeInstaller::from_jar(resp.bytes()?.to_vec())
+    }
+
+    pub fn from_jar(jar: Vec<u8>) -> Result<Self> {
+        let profile: InstallProfile = serde_json::from_slice(&read_entry(&jar, "install_profile.json")?)
+            .context("Only installers of Forge 1.13 and newer are supported")?;
+        let version = serde_json::from_slice(&read_entry(&jar, &profile.json)?)?;
+        Ok(ForgeInstaller { profile, version, jar })
+    }
+
+    pub fn read_file(&self, name: &str) -> Result<Vec<u8>> {
+        read_entry(&self.jar, name)
+    }
+
+    pub fn has_file(&self, name: &str) -> bool {
+        ZipArchive::new(Cursor::new(&self.jar))
+            .map(|mut zip| zip.by_name(name.trim_start_matches('/')).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+fn read_entry(jar: &[u8], name: &str) -> Result<Vec<u8>> {
+    let mut zip = ZipArchive::new(Cursor::new(jar))?;
+    let mut file = zip
+        .by_name(name.trim_start_matches('/'))
+        .with_context(|| format!("Can't find {} in forge installer", name))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+pub fn installer_url(version: &str) -> String {
+    format!(
+        "https://maven.minecraftforge.net/net/minecraftforge/forge/{ver}/forge-{ver}-installer.jar",
+        ver = version
+    )
+}
+
+/// Accepts a path to a local installer jar or a Forge version like `1.16.5-36.2.39`.
+impl FromStr for ForgeInstaller {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if Path::new(s).is_file() {
+            ForgeInstaller::from_jar(fs::read(s)?)
+        } else {
+            ForgeInstaller::download(&installer_url(s))
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::util::{generate_download_url, generate_lib_path};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Libraries {
@@ -13,48 +15,142 @@ pub struct AssetIndex {
     pub id: String,
     pub url: String,
     pub sha1: String,
+    pub size: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Library {
+    #[serde(default)]
     pub downloads: Downloads,
     pub name: String,
+    /// Maven repository of libraries given by name only, as in Fabric profile JSONs.
+    pub url: Option<String>,
     pub rules: Option<Vec<Rule>>,
+    /// OS name to natives classifier, `${arch}` stands for `32` or `64`.
+    pub natives: Option<HashMap<String, String>>,
+    pub extract: Option<Extract>,
+}
+
+impl Library {
+    /// Main artifact, libraries without `downloads` are resolved from their maven `url`.
+    pub fn artifact(&self) -> Option<File> {
+        if let Some(artifact) = &self.downloads.artifact {
+            return Some(artifact.clone());
+        }
+        let url = self.url.as_ref()?;
+        Some(File {
+            path: Some(generate_lib_path(&self.name)),
+            url: generate_download_url(url, &self.name),
+            sha1: None,
+            size: None,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct Extract {
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Downloads {
     pub artifact: Option<File>,
     pub server: Option<File>,
     pub client: Option<File>,
-    pub classifiers: Option<Classifier>,
+    pub classifiers: Option<HashMap<String, File>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct File {
     pub path: Option<String>,
     pub url: String,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// `arguments` of 1.13+ version JSONs.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Arguments {
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+impl Arguments {
+    /// Game arguments from the `minecraftArguments` string of versions before 1.13.
+    pub fn from_legacy(arguments: &str) -> Self {
+        Arguments {
+            game: arguments
+                .split_whitespace()
+                .map(|argument| Argument::Plain(argument.to_string()))
+                .collect(),
+            jvm: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional { rules: Vec<Rule>, value: ArgumentValue },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl ArgumentValue {
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            ArgumentValue::Single(value) => vec![value],
+            ArgumentValue::Many(values) => values.iter().map(|v| v.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Logging {
+    pub client: Option<LoggingConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoggingConfig {
+    /// JVM argument with `${path}` standing for the downloaded file.
+    pub argument: String,
+    pub file: LoggingFile,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoggingFile {
+    pub id: String,
     pub sha1: String,
+    pub size: Option<u64>,
+    pub url: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Rule {
-    pub action: String,
+    pub action: RuleAction,
     pub os: Option<Os>,
+    pub features: Option<HashMap<String, bool>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Os {
-    pub name: String,
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Classifier {
-    #[serde(rename = "natives-linux")]
-    pub natives_linux: Option<File>,
-    #[serde(rename = "natives-osx")]
-    pub natives_osx: Option<File>,
-    #[serde(rename = "natives-windows")]
-    pub natives_windows: Option<File>,
-    pub sources: Option<File>,
+pub struct Os {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub arch: Option<String>,
 }
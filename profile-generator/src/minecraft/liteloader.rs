@@ -0,0 +1,71 @@
+use crate::minecraft::version::NameLibrary;
+use launcher_extension_api::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Serialize)]
+pub struct LiteLoaderVersions {
+    pub versions: HashMap<String, LiteLoaderGameVersion>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LiteLoaderGameVersion {
+    pub repo: LiteLoaderRepo,
+    pub artefacts: Option<LiteLoaderArtefacts>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LiteLoaderRepo {
+    pub url: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LiteLoaderArtefacts {
+    #[serde(rename = "com.mumfrey:liteloader")]
+    pub liteloader: HashMap<String, LiteLoaderArtefact>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct LiteLoaderArtefact {
+    #[serde(rename = "tweakClass")]
+    pub tweak_class: String,
+    #[serde(default)]
+    pub libraries: Vec<NameLibrary>,
+    pub version: String,
+}
+
+/// LiteLoader build for one Minecraft version with the repository it is published in.
+pub struct LiteLoader {
+    pub artefact: LiteLoaderArtefact,
+    pub repo_url: String,
+}
+
+impl LiteLoader {
+    /// Looks up `version` of LiteLoader for `game_version`, `latest` selects the
+    /// newest build.
+    pub fn resolve(game_version: &str, version: &str) -> Result<Self> {
+        let versions = reqwest::blocking::get("https://dl.liteloader.com/versions/versions.json")?
+            .json::<LiteLoaderVersions>()?;
+        let game = versions
+            .versions
+            .get(game_version)
+            .with_context(|| format!("LiteLoader has no builds for Minecraft {}", game_version))?;
+        let builds = &game
+            .artefacts
+            .as_ref()
+            .with_context(|| format!("LiteLoader has no builds for Minecraft {}", game_version))?
+            .liteloader;
+        let artefact = builds
+            .get(version)
+            .or_else(|| builds.values().find(|artefact| artefact.version == version))
+            .with_context(|| format!("Can't find LiteLoader {} for Minecraft {}", version, game_version))?;
+        Ok(LiteLoader {
+            artefact: artefact.clone(),
+            repo_url: game.repo.url.clone(),
+        })
+    }
+
+    pub fn name(&self) -> String {
+        format!("com.mumfrey:liteloader:{}", self.artefact.version)
+    }
+}
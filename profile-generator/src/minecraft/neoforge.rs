@@ -0,0 +1,39 @@
+use crate::minecraft::installer::ForgeInstaller;
+use launcher_extension_api::{Error, Result};
+use std::str::FromStr;
+
+const NEOFORGED_MAVEN: &str = "https://maven.neoforged.net/releases/";
+
+/// NeoForge ships the same installer format as modern Forge, its profile is built by
+/// running the installer processors.
+pub struct NeoForgeManifest {
+    pub installer: ForgeInstaller,
+}
+
+impl FromStr for NeoForgeManifest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(NeoForgeManifest {
+            installer: ForgeInstaller::download(&installer_url(s))?,
+        })
+    }
+}
+
+/// Installer on the NeoForged maven. The 1.20.1 builds (`47.x`) were still published
+/// as `net.neoforged:forge:1.20.1-<version>`.
+pub fn installer_url(version: &str) -> String {
+    let (artifact, version) = if version.starts_with("1.20.1-") {
+        ("forge", version.to_string())
+    } else if version.starts_with("47.") {
+        ("forge", format!("1.20.1-{}", version))
+    } else {
+        ("neoforge", version.to_string())
+    };
+    format!(
+        "{maven}net/neoforged/{artifact}/{ver}/{artifact}-{ver}-installer.jar",
+        maven = NEOFORGED_MAVEN,
+        artifact = artifact,
+        ver = version
+    )
+}
@@ -0,0 +1,57 @@
+use crate::minecraft::fabric::{FabricLibraries, FabricMainClass};
+use crate::minecraft::version::NameLibrary;
+use launcher_extension_api::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const QUILT_MAVEN: &str = "https://maven.quiltmc.org/repository/release/";
+const FABRIC_MAVEN: &str = "https://maven.fabricmc.net/";
+
+/// Quilt publishes its loader manifest in the same format as Fabric.
+#[derive(Deserialize, Serialize)]
+pub struct QuiltLoaderManifest {
+    pub version: i32,
+    pub libraries: FabricLibraries,
+    #[serde(rename = "mainClass")]
+    pub main_class: FabricMainClass,
+    #[serde(skip)]
+    pub loader_version: String,
+}
+
+impl QuiltLoaderManifest {
+    /// Client libraries with the loader itself and the hashed and intermediary mappings
+    /// for `game_version`, which the manifest does not list.
+    pub fn client_libraries(&mut self, game_version: &str) -> Vec<NameLibrary> {
+        let mut libraries = Vec::new();
+        libraries.append(&mut self.libraries.client);
+        libraries.append(&mut self.libraries.common);
+        libraries.push(NameLibrary {
+            name: format!("org.quiltmc:quilt-loader:{}", self.loader_version),
+            url: QUILT_MAVEN.to_string(),
+        });
+        libraries.push(NameLibrary {
+            name: format!("org.quiltmc:hashed:{}", game_version),
+            url: QUILT_MAVEN.to_string(),
+        });
+        libraries.push(NameLibrary {
+            name: format!("net.fabricmc:intermediary:{}", game_version),
+            url: FABRIC_MAVEN.to_string(),
+        });
+        libraries
+    }
+}
+
+impl FromStr for QuiltLoaderManifest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let url: String = format!(
+            "{maven}org/quiltmc/quilt-loader/{ver}/quilt-loader-{ver}.json",
+            maven = QUILT_MAVEN,
+            ver = s
+        );
+        let mut manifest = reqwest::blocking::get(&url)?.json::<QuiltLoaderManifest>()?;
+        manifest.loader_version = s.to_string();
+        Ok(manifest)
+    }
+}
@@ -1,8 +1,14 @@
-use crate::minecraft::libraries::{AssetIndex, Downloads, Library};
-use launcher_extension_api::{Error, Result};
+use crate::minecraft::libraries::{Arguments, AssetIndex, Downloads, Library, Logging};
+use crate::util::artifact_key;
+use launcher_extension_api::{Context, Error, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 
+const MAX_INHERITANCE_DEPTH: usize = 10;
+
 #[derive(Serialize, Deserialize)]
 pub struct VersionManifest {
     pub latest: Latest,
@@ -11,8 +17,8 @@ pub struct VersionManifest {
 
 #[derive(Serialize, Deserialize)]
 pub struct Latest {
-    release: String,
-    snapshot: String,
+    pub release: String,
+    pub snapshot: String,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -32,9 +38,154 @@ pub struct Libraries {
     pub asset_index: AssetIndex,
     pub downloads: Downloads,
     pub libraries: Vec<Library>,
+    pub arguments: Option<Arguments>,
+    /// Game arguments of versions before 1.13 as one string.
+    #[serde(rename = "minecraftArguments")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(rename = "mainClass")]
+    pub main_class: Option<String>,
+    pub logging: Option<Logging>,
+    /// Resolved from a version JSON given by the user, its main class and logging config
+    /// are used as declared. Vanilla profiles keep the generator defaults.
+    #[serde(skip)]
+    pub custom: bool,
+    /// Minecraft version a resolved version JSON builds on, the root of its
+    /// `inheritsFrom` chain.
+    #[serde(skip)]
+    pub game_version: Option<String>,
+}
+
+/// Version JSON that may only declare what it changes on top of the version it
+/// `inheritsFrom`, like loader profiles and Forge installer versions.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialVersion {
+    pub id: Option<String>,
+    pub inherits_from: Option<String>,
+    pub main_class: Option<String>,
+    pub asset_index: Option<AssetIndex>,
+    pub downloads: Option<Downloads>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    pub arguments: Option<Arguments>,
+    pub minecraft_arguments: Option<String>,
+    pub logging: Option<Logging>,
 }
 
-#[derive(Deserialize, Serialize)]
+impl PartialVersion {
+    /// Reads a version JSON from a local file or an url.
+    pub fn load(location: &str) -> Result<Self> {
+        let version = if Path::new(location).is_file() {
+            serde_json::from_slice(&fs::read(location)?)?
+        } else {
+            reqwest::blocking::get(location)?.error_for_status()?.json::<PartialVersion>()?
+        };
+        Ok(version)
+    }
+
+    /// Merges the version with all its parents. Parents are looked up in `local_folder`
+    /// as `<id>.json` or `<id>/<id>.json` first, then in the Mojang manifest.
+    pub fn resolve(self, local_folder: Option<&Path>) -> Result<Libraries> {
+        let mut version = self;
+        let mut root_id = None;
+        let mut depth = 0;
+        while let Some(parent_id) = version.inherits_from.take() {
+            depth += 1;
+            if depth > MAX_INHERITANCE_DEPTH {
+                return Err(launcher_extension_api::anyhow!("Too deep inheritsFrom chain at {}", parent_id));
+            }
+            let local_parent = local_folder.and_then(|folder| {
+                vec![
+                    folder.join(format!("{}.json", parent_id)),
+                    folder.join(&parent_id).join(format!("{}.json", parent_id)),
+                ]
+                .into_iter()
+                .find(|path| path.is_file())
+            });
+            let parent = match local_parent {
+                Some(path) => serde_json::from_slice(&fs::read(path)?)?,
+                None => reqwest::blocking::get(&find_version(&parent_id)?.url)?.json::<PartialVersion>()?,
+            };
+            version = version.merge(parent);
+            root_id = Some(parent_id);
+        }
+        let game_version = root_id
+            .or_else(|| version.id.clone())
+            .context("Version JSON has neither an id nor inheritsFrom")?;
+        Ok(Libraries {
+            asset_index: version.asset_index.context("Version JSON has no assetIndex")?,
+            downloads: version.downloads.context("Version JSON has no downloads")?,
+            libraries: version.libraries,
+            arguments: version.arguments,
+            minecraft_arguments: version.minecraft_arguments,
+            main_class: version.main_class,
+            logging: version.logging,
+            custom: true,
+            game_version: Some(game_version),
+        })
+    }
+
+    /// Values of the child win, its libraries come first and replace parent libraries
+    /// of the same artifact, arguments are appended to the parent ones.
+    fn merge(self, parent: PartialVersion) -> PartialVersion {
+        let known: HashSet<String> = self.libraries.iter().map(|lib| artifact_key(&lib.name)).collect();
+        let mut libraries = self.libraries;
+        libraries.extend(
+            parent
+                .libraries
+                .into_iter()
+                .filter(|lib| !known.contains(&artifact_key(&lib.name))),
+        );
+        let arguments = match (parent.arguments, self.arguments) {
+            (Some(mut parent), Some(child)) => {
+                parent.game.extend(child.game);
+                parent.jvm.extend(child.jvm);
+                Some(parent)
+            }
+            (parent, child) => child.or(parent),
+        };
+        PartialVersion {
+            id: self.id.or(parent.id),
+            inherits_from: parent.inherits_from,
+            main_class: self.main_class.or(parent.main_class),
+            asset_index: self.asset_index.or(parent.asset_index),
+            downloads: self.downloads.or(parent.downloads),
+            libraries,
+            arguments,
+            minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+            logging: self.logging.or(parent.logging),
+        }
+    }
+}
+
+fn get_manifest() -> Result<VersionManifest> {
+    let manifest = reqwest::blocking::get(
+        "https://launchermeta.mojang.com/mc/game/version_manifest.json",
+    )?
+    .json::<VersionManifest>()?;
+    Ok(manifest)
+}
+
+/// Resolves `latest` and `latest-snapshot` to the current version id, other ids are
+/// returned as they are.
+pub fn resolve_version_id(id: &str) -> Result<String> {
+    match id {
+        "latest" => Ok(get_manifest()?.latest.release),
+        "latest-snapshot" => Ok(get_manifest()?.latest.snapshot),
+        _ => Ok(id.to_string()),
+    }
+}
+
+fn find_version(id: &str) -> Result<Version> {
+    let id = resolve_version_id(id)?;
+    get_manifest()?
+        .versions
+        .into_iter()
+        .find(|v| v.id.eq(&id))
+        .ok_or(launcher_extension_api::anyhow!("Incorrect minecraft version"))
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct NameLibrary {
     pub name: String,
     #[serde(default = "default_lib")]
@@ -49,16 +200,113 @@ impl FromStr for Libraries {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let manifest = reqwest::blocking::get(
-            "https://launchermeta.mojang.com/mc/game/version_manifest.json",
-        )?
-        .json::<VersionManifest>()?;
-        let version = manifest
-            .versions
-            .iter()
-            .find(|v| v.id.eq(s))
-            .ok_or(launcher_extension_api::anyhow!("Incorrect minecraft version"))?;
+        let version = find_version(s)?;
         let libs = reqwest::blocking::get(&version.url)?.json::<Libraries>()?;
         Ok(libs)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minecraft::libraries::Argument;
+
+    fn partial(json: &str) -> PartialVersion {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn plain(arguments: &[Argument]) -> Vec<&str> {
+        arguments
+            .iter()
+            .map(|argument| match argument {
+                Argument::Plain(value) => value.as_str(),
+                Argument::Conditional { .. } => "<conditional>",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn child_values_win() {
+        let child = partial(r#"{"id": "fabric", "inheritsFrom": "1.20.1", "mainClass": "net.fabricmc.Knot"}"#);
+        let parent = partial(
+            r#"{"id": "1.20.1", "mainClass": "net.minecraft.client.main.Main",
+                "assetIndex": {"id": "5", "url": "https://example.com/5.json", "sha1": "abc", "size": 1}}"#,
+        );
+        let merged = child.merge(parent);
+        assert_eq!(merged.id.as_deref(), Some("fabric"));
+        assert_eq!(merged.main_class.as_deref(), Some("net.fabricmc.Knot"));
+        assert_eq!(merged.asset_index.map(|index| index.id).as_deref(), Some("5"));
+        assert!(merged.inherits_from.is_none());
+    }
+
+    #[test]
+    fn child_libraries_replace_parent_artifacts() {
+        let child = partial(r#"{"libraries": [{"name": "org.ow2.asm:asm:9.5"}, {"name": "net.fabricmc:fabric-loader:0.15.0"}]}"#);
+        let parent = partial(r#"{"libraries": [{"name": "org.ow2.asm:asm:9.1"}, {"name": "com.mojang:brigadier:1.1.8"}]}"#);
+        let merged = child.merge(parent);
+        let names: Vec<&str> = merged.libraries.iter().map(|lib| lib.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["org.ow2.asm:asm:9.5", "net.fabricmc:fabric-loader:0.15.0", "com.mojang:brigadier:1.1.8"]
+        );
+    }
+
+    #[test]
+    fn arguments_are_appended_to_the_parent() {
+        let child = partial(r#"{"arguments": {"game": ["--launchTarget", "forgeclient"]}}"#);
+        let parent = partial(r#"{"arguments": {"game": ["--username", "${auth_player_name}"], "jvm": ["-cp", "${classpath}"]}}"#);
+        let arguments = child.merge(parent).arguments.unwrap();
+        assert_eq!(plain(&arguments.game), vec!["--username", "${auth_player_name}", "--launchTarget", "forgeclient"]);
+        assert_eq!(plain(&arguments.jvm), vec!["-cp", "${classpath}"]);
+    }
+
+    #[test]
+    fn parents_are_found_next_to_the_version() {
+        let folder = std::env::temp_dir().join(format!("profilegen-versions-{}", std::process::id()));
+        fs::create_dir_all(folder.join("base")).unwrap();
+        fs::write(
+            folder.join("base").join("base.json"),
+            r#"{"id": "base", "mainClass": "a.Main", "libraries": [{"name": "a:b:1"}],
+                "assetIndex": {"id": "5", "url": "https://example.com/5.json", "sha1": "abc", "size": 1},
+                "downloads": {}}"#,
+        )
+        .unwrap();
+        let version = partial(r#"{"id": "child", "inheritsFrom": "base", "libraries": [{"name": "c:d:1"}]}"#);
+        let resolved = version.resolve(Some(&folder)).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+        assert!(resolved.custom);
+        assert_eq!(resolved.game_version.as_deref(), Some("base"));
+        assert_eq!(resolved.main_class.as_deref(), Some("a.Main"));
+        assert_eq!(resolved.asset_index.id, "5");
+        assert_eq!(resolved.libraries.len(), 2);
+    }
+
+    #[test]
+    fn inheritance_cycles_stop_at_the_depth_limit() {
+        let folder = std::env::temp_dir().join(format!("profilegen-cycle-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("loop.json"), r#"{"id": "loop", "inheritsFrom": "loop"}"#).unwrap();
+        let version = partial(r#"{"id": "start", "inheritsFrom": "loop"}"#);
+        let result = version.resolve(Some(&folder));
+        fs::remove_dir_all(&folder).unwrap();
+        let error = result.err().expect("cycle must fail").to_string();
+        assert!(error.contains("Too deep inheritsFrom chain"), "{}", error);
+    }
+
+    #[test]
+    fn standalone_version_is_its_own_game_version() {
+        let version = partial(
+            r#"{"id": "1.20.1-custom",
+                "assetIndex": {"id": "5", "url": "https://example.com/5.json", "sha1": "abc", "size": 1},
+                "downloads": {}}"#,
+        );
+        let resolved = version.resolve(None).unwrap();
+        assert_eq!(resolved.game_version.as_deref(), Some("1.20.1-custom"));
+    }
+
+    #[test]
+    fn resolved_version_needs_an_asset_index() {
+        let error = partial(r#"{"id": "broken"}"#).resolve(None).err().unwrap().to_string();
+        assert!(error.contains("assetIndex"), "{}", error);
+    }
+}
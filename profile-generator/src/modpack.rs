@@ -0,0 +1,202 @@
+use crate::minecraft::installer::{self, ForgeInstaller};
+use crate::minecraft::GameType;
+use launcher_extension_api::Result;
+use path_slash::PathBufExt;
+use std::fs::{copy, create_dir_all, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+pub mod curseforge;
+pub mod instance;
+pub mod modrinth;
+
+/// Modpack resolved to the game it runs on and the files it adds to the profile folder.
+pub struct ModPack {
+    pub game_version: String,
+    pub loader: Option<PackLoader>,
+    pub files: Vec<PackFile>,
+    pub overrides: Vec<Overrides>,
+    /// JVM arguments the pack needs on top of the loader ones.
+    pub jvm_args: Vec<String>,
+}
+
+pub enum PackLoader {
+    Forge(String),
+    NeoForge(String),
+    Fabric(String),
+    Quilt(String),
+}
+
+/// File downloaded into the profile folder, `path` is relative to it.
+pub struct PackFile {
+    pub path: PathBuf,
+    pub url: String,
+    /// Tried in order when `url` fails.
+    pub fallback_urls: Vec<String>,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+    pub size: Option<u64>,
+    /// Name of the toggle when the player may leave the file out, such files start
+    /// disabled.
+    pub optional: Option<String>,
+}
+
+/// Files copied as they are into the profile folder. Files ending in `.disabled` were
+/// switched off in the launcher they come from and are skipped.
+#[derive(Clone)]
+pub enum Overrides {
+    /// Entries below each prefix of a zip archive go to the paired profile sub-folder,
+    /// later prefixes win.
+    Archive { path: PathBuf, prefixes: Vec<(String, PathBuf)> },
+    /// Files below each folder go to the paired profile sub-folder.
+    Folder { folders: Vec<(PathBuf, PathBuf)> },
+    /// Each file, relative to `folder`, goes to the paired path of the profile.
+    Files { folder: PathBuf, files: Vec<(PathBuf, PathBuf)> },
+}
+
+impl ModPack {
+    pub fn game_type(&self) -> Result<GameType> {
+        let game_type = match &self.loader {
+            Some(PackLoader::Forge(version)) if uses_forge_installer(&self.game_version) => GameType::ForgeInstaller(
+                ForgeInstaller::download(&installer::installer_url(&format!("{}-{}", self.game_version, version)))?,
+            ),
+            Some(PackLoader::Forge(version)) => GameType::Forge(version.parse()?),
+            Some(PackLoader::NeoForge(version)) => GameType::NeoForge(version.parse()?),
+            Some(PackLoader::Fabric(version)) => GameType::Fabric(version.parse()?),
+            Some(PackLoader::Quilt(version)) => GameType::Quilt(version.parse()?),
+            None => GameType::Vanilla,
+        };
+        Ok(game_type)
+    }
+}
+
+const DISABLED_SUFFIX: &str = ".disabled";
+
+/// Forge for Minecraft 1.13 and newer only installs through its installer.
+fn uses_forge_installer(game_version: &str) -> bool {
+    let minor = game_version.split('.').nth(1).and_then(|minor| minor.parse::<u32>().ok());
+    matches!(minor, Some(minor) if minor >= 13)
+}
+
+impl Overrides {
+    /// Copies the files into `profile_folder` and returns how many were copied.
+    pub fn apply(&self, profile_folder: &Path) -> Result<usize> {
+        let mut copied = 0;
+        match self {
+            Overrides::Archive { path, prefixes } => {
+                let mut zip = ZipArchive::new(File::open(path)?)?;
+                for (prefix, target_folder) in prefixes {
+                    for index in 0..zip.len() {
+                        let mut file = zip.by_index(index)?;
+                        if !file.is_file() || file.name().ends_with(DISABLED_SUFFIX) {
+                            continue;
+                        }
+                        let relative = match file.enclosed_name().and_then(|name| name.strip_prefix(prefix).ok()) {
+                            Some(relative) => relative.to_path_buf(),
+                            None => continue,
+                        };
+                        let target = profile_folder.join(target_folder).join(relative);
+                        if let Some(parent) = target.parent() {
+                            create_dir_all(parent)?;
+                        }
+                        io::copy(&mut file, &mut File::create(target)?)?;
+                        copied += 1;
+                    }
+                }
+            }
+            Overrides::Folder { folders } => {
+                for (folder, target_folder) in folders {
+                    for entry in WalkDir::new(folder) {
+                        let entry = entry?;
+                        let disabled = entry.file_name().to_string_lossy().ends_with(DISABLED_SUFFIX);
+                        if !entry.file_type().is_file() || disabled {
+                            continue;
+                        }
+                        let target = profile_folder
+                            .join(target_folder)
+                            .join(entry.path().strip_prefix(folder)?);
+                        if let Some(parent) = target.parent() {
+                            create_dir_all(parent)?;
+                        }
+                        copy(entry.path(), target)?;
+                        copied += 1;
+                    }
+                }
+            }
+            Overrides::Files { folder, files } => {
+                for (file, target) in files {
+                    if file.to_string_lossy().ends_with(DISABLED_SUFFIX) {
+                        continue;
+                    }
+                    let target = profile_folder.join(target);
+                    if let Some(parent) = target.parent() {
+                        create_dir_all(parent)?;
+                    }
+                    copy(folder.join(file), target)?;
+                    copied += 1;
+                }
+            }
+        }
+        Ok(copied)
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Overrides::Archive { path, prefixes } => {
+                let prefixes: Vec<&str> = prefixes.iter().map(|(prefix, _)| prefix.as_str()).collect();
+                format!("{} ({})", path.display(), prefixes.join(", "))
+            }
+            Overrides::Folder { folders } => {
+                let folders: Vec<String> = folders.iter().map(|(folder, _)| folder.display().to_string()).collect();
+                folders.join(", ")
+            }
+            Overrides::Files { folder, .. } => folder.display().to_string(),
+        }
+    }
+}
+
+/// Relative path from a modpack, paths leaving the profile folder are rejected.
+pub fn pack_path(path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from_slash(path);
+    let safe = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if safe {
+        Ok(path)
+    } else {
+        Err(launcher_extension_api::anyhow!("Modpack file path {} leaves the profile folder", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_path_keeps_relative_paths() {
+        let path = pack_path("mods/sodium.jar").unwrap();
+        assert_eq!(path, Path::new("mods").join("sodium.jar"));
+    }
+
+    #[test]
+    fn pack_path_rejects_parent_folders() {
+        assert!(pack_path("../mods/sodium.jar").is_err());
+        assert!(pack_path("mods/../../sodium.jar").is_err());
+    }
+
+    #[test]
+    fn pack_path_rejects_absolute_paths() {
+        assert!(pack_path("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn forge_installer_from_1_13() {
+        assert!(!uses_forge_installer("1.12.2"));
+        assert!(!uses_forge_installer("1.7.10"));
+        assert!(uses_forge_installer("1.13.2"));
+        assert!(uses_forge_installer("1.20.1"));
+        assert!(!uses_forge_installer("b1.7.3"));
+    }
+}
@@ -0,0 +1,342 @@
+use crate::modpack::{pack_path, ModPack, Overrides, PackFile, PackLoader};
+use launcher_extension_api::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Hash algorithm id of SHA-1 in the CurseForge API.
+const SHA1_ALGO: u32 = 1;
+
+/// CurseForge project classes and the profile folder their files go to.
+const CLASS_FOLDERS: [(u32, &str); 3] = [(6, "mods"), (12, "resourcepacks"), (6552, "shaderpacks")];
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CurseForgeConfig {
+    /// Base url of the CurseForge API or of a compatible stand-in.
+    pub endpoint: String,
+    pub api_key: Option<String>,
+}
+
+impl Default for CurseForgeConfig {
+    fn default() -> Self {
+        CurseForgeConfig {
+            endpoint: "https://api.curseforge.com".to_string(),
+            api_key: None,
+        }
+    }
+}
+
+/// `manifest.json` of a CurseForge modpack export.
+#[derive(Deserialize, Serialize)]
+pub struct CurseForgeManifest {
+    pub minecraft: CurseForgeMinecraft,
+    pub files: Vec<CurseForgeManifestFile>,
+    pub overrides: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeMinecraft {
+    pub version: String,
+    #[serde(default)]
+    pub mod_loaders: Vec<CurseForgeModLoader>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CurseForgeModLoader {
+    pub id: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CurseForgeManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u64,
+    #[serde(rename = "fileID")]
+    pub file_id: u64,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+struct FilesRequest<'a> {
+    #[serde(rename = "fileIds")]
+    file_ids: &'a [u64],
+}
+
+#[derive(Serialize)]
+struct ModsRequest<'a> {
+    #[serde(rename = "modIds")]
+    mod_ids: &'a [u64],
+}
+
+#[derive(Deserialize, Serialize)]
+struct ApiResponse<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeMod {
+    pub id: u64,
+    pub name: String,
+    pub class_id: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeFile {
+    pub id: u64,
+    pub mod_id: u64,
+    pub file_name: String,
+    pub download_url: Option<String>,
+    #[serde(default)]
+    pub hashes: Vec<CurseForgeHash>,
+    pub file_length: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CurseForgeHash {
+    pub value: String,
+    pub algo: u32,
+}
+
+pub fn read_curseforge(path: &Path, config: &CurseForgeConfig) -> Result<ModPack> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let manifest: CurseForgeManifest = serde_json::from_reader(zip.by_name("manifest.json")?)?;
+    let loader = manifest
+        .minecraft
+        .mod_loaders
+        .iter()
+        .find(|loader| loader.primary)
+        .or_else(|| manifest.minecraft.mod_loaders.first())
+        .map(|loader| parse_loader(&loader.id))
+        .transpose()?;
+    let required: HashMap<u64, bool> = manifest
+        .files
+        .iter()
+        .map(|file| (file.file_id, file.required))
+        .collect();
+    let file_ids: Vec<u64> = manifest.files.iter().map(|file| file.file_id).collect();
+    let files: Vec<CurseForgeFile> = if file_ids.is_empty() {
+        vec![]
+    } else {
+        post(config, "/v1/mods/files", &FilesRequest { file_ids: &file_ids })?
+    };
+    let mut mod_ids: Vec<u64> = files.iter().map(|file| file.mod_id).collect();
+    mod_ids.sort_unstable();
+    mod_ids.dedup();
+    let mods: HashMap<u64, CurseForgeMod> = if mod_ids.is_empty() {
+        HashMap::new()
+    } else {
+        post::<_, CurseForgeMod>(config, "/v1/mods", &ModsRequest { mod_ids: &mod_ids })?
+            .into_iter()
+            .map(|project| (project.id, project))
+            .collect()
+    };
+    let pack_files = pack_files(&required, files, &mods)?;
+    Ok(ModPack {
+        game_version: manifest.minecraft.version,
+        loader,
+        files: pack_files,
+        overrides: vec![Overrides::Archive {
+            path: path.to_path_buf(),
+            prefixes: vec![(
+                manifest.overrides.unwrap_or_else(|| "overrides".to_string()),
+                PathBuf::new(),
+            )],
+        }],
+        jvm_args: vec![],
+    })
+}
+
+/// Places every file by the class of its project, files the manifest doesn't require
+/// become optionals named after their project.
+fn pack_files(
+    required: &HashMap<u64, bool>,
+    files: Vec<CurseForgeFile>,
+    mods: &HashMap<u64, CurseForgeMod>,
+) -> Result<Vec<PackFile>> {
+    let mut pack_files = Vec::with_capacity(files.len());
+    let mut unavailable = Vec::new();
+    for file in files {
+        let url = match &file.download_url {
+            Some(url) => url.clone(),
+            None => {
+                unavailable.push(format!("{} (project {}, file {})", file.file_name, file.mod_id, file.id));
+                continue;
+            }
+        };
+        let project = mods.get(&file.mod_id);
+        let class_id = project.and_then(|project| project.class_id);
+        let folder = match CLASS_FOLDERS.iter().find(|(id, _)| Some(*id) == class_id) {
+            Some((_, folder)) => folder,
+            None => {
+                log::warn!(
+                    "{} (project {}) is not a mod, resource pack or shader pack, adding it to mods",
+                    file.file_name,
+                    file.mod_id
+                );
+                "mods"
+            }
+        };
+        let optional = match required.get(&file.id) {
+            Some(false) => Some(project.map_or_else(|| file.file_name.clone(), |project| project.name.clone())),
+            _ => None,
+        };
+        pack_files.push(PackFile {
+            path: pack_path(folder)?.join(pack_path(&file.file_name)?),
+            url,
+            fallback_urls: vec![],
+            sha1: file
+                .hashes
+                .iter()
+                .find(|hash| hash.algo == SHA1_ALGO)
+                .map(|hash| hash.value.clone()),
+            sha512: None,
+            size: file.file_length,
+            optional,
+        });
+    }
+    if !unavailable.is_empty() {
+        return Err(launcher_extension_api::anyhow!(
+            "CurseForge does not allow downloading these files, add them manually:\n{}",
+            unavailable.join("\n")
+        ));
+    }
+    Ok(pack_files)
+}
+
+/// Loader ids look like `forge-47.2.0` or `fabric-0.15.3`.
+fn parse_loader(id: &str) -> Result<PackLoader> {
+    let (name, version) = id
+        .split_once('-')
+        .with_context(|| format!("Invalid mod loader {}", id))?;
+    let version = version.to_string();
+    match name {
+        "forge" => Ok(PackLoader::Forge(version)),
+        "neoforge" => Ok(PackLoader::NeoForge(version)),
+        "fabric" => Ok(PackLoader::Fabric(version)),
+        "quilt" => Ok(PackLoader::Quilt(version)),
+        _ => Err(launcher_extension_api::anyhow!("Unsupported mod loader {}", id)),
+    }
+}
+
+fn post<B: Serialize, T: DeserializeOwned>(config: &CurseForgeConfig, path: &str, body: &B) -> Result<Vec<T>> {
+    let mut request = reqwest::blocking::Client::new()
+        .post(format!("{}{}", config.endpoint.trim_end_matches('/'), path))
+        .json(body);
+    if let Some(api_key) = &config.api_key {
+        request = request.header("x-api-key", api_key);
+    }
+    let response = request
+        .send()?
+        .error_for_status()
+        .context("CurseForge API request failed, check the endpoint and api key in config/profilegen.json")?
+        .json::<ApiResponse<T>>()?;
+    Ok(response.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loaders_are_parsed_by_prefix() {
+        assert!(matches!(parse_loader("forge-47.2.0"), Ok(PackLoader::Forge(v)) if v == "47.2.0"));
+        assert!(matches!(parse_loader("neoforge-20.4.80"), Ok(PackLoader::NeoForge(v)) if v == "20.4.80"));
+        assert!(matches!(parse_loader("fabric-0.15.3"), Ok(PackLoader::Fabric(v)) if v == "0.15.3"));
+        assert!(matches!(parse_loader("quilt-0.23.1"), Ok(PackLoader::Quilt(v)) if v == "0.23.1"));
+    }
+
+    #[test]
+    fn unknown_and_malformed_loaders_fail() {
+        assert!(parse_loader("rift-1.0.0").is_err());
+        assert!(parse_loader("forge").is_err());
+        assert!(parse_loader("").is_err());
+    }
+
+    #[test]
+    fn files_are_placed_by_project_class() {
+        let files: ApiResponse<CurseForgeFile> = serde_json::from_str(
+            r#"{"data": [
+                {"id": 1, "modId": 10, "fileName": "jei.jar", "downloadUrl": "https://edge.forgecdn.net/jei.jar",
+                 "hashes": [{"value": "abc", "algo": 1}], "fileLength": 5},
+                {"id": 2, "modId": 20, "fileName": "faithful.zip", "downloadUrl": "https://edge.forgecdn.net/faithful.zip"},
+                {"id": 3, "modId": 30, "fileName": "bsl.zip", "downloadUrl": "https://edge.forgecdn.net/bsl.zip"},
+                {"id": 4, "modId": 40, "fileName": "world.zip", "downloadUrl": "https://edge.forgecdn.net/world.zip"}
+            ]}"#,
+        )
+        .unwrap();
+        let mods: ApiResponse<CurseForgeMod> = serde_json::from_str(
+            r#"{"data": [
+                {"id": 10, "name": "JEI", "classId": 6},
+                {"id": 20, "name": "Faithful", "classId": 12},
+                {"id": 30, "name": "BSL", "classId": 6552},
+                {"id": 40, "name": "Skyblock", "classId": 17}
+            ]}"#,
+        )
+        .unwrap();
+        let mods = mods.data.into_iter().map(|project| (project.id, project)).collect();
+        let required = vec![(1, true), (2, true), (3, true), (4, true)].into_iter().collect();
+        let pack_files = pack_files(&required, files.data, &mods).unwrap();
+        let paths: Vec<PathBuf> = pack_files.iter().map(|file| file.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("mods").join("jei.jar"),
+                Path::new("resourcepacks").join("faithful.zip"),
+                Path::new("shaderpacks").join("bsl.zip"),
+                Path::new("mods").join("world.zip"),
+            ]
+        );
+        assert_eq!(pack_files[0].sha1.as_deref(), Some("abc"));
+        assert_eq!(pack_files[0].size, Some(5));
+    }
+
+    #[test]
+    fn files_not_required_become_optionals() {
+        let files: ApiResponse<CurseForgeFile> = serde_json::from_str(
+            r#"{"data": [
+                {"id": 1, "modId": 10, "fileName": "jei.jar", "downloadUrl": "https://edge.forgecdn.net/jei.jar"},
+                {"id": 2, "modId": 20, "fileName": "journeymap.jar", "downloadUrl": "https://edge.forgecdn.net/journeymap.jar"},
+                {"id": 3, "modId": 30, "fileName": "unknown.jar", "downloadUrl": "https://edge.forgecdn.net/unknown.jar"}
+            ]}"#,
+        )
+        .unwrap();
+        let mods = vec![(
+            20,
+            CurseForgeMod {
+                id: 20,
+                name: "JourneyMap".to_string(),
+                class_id: Some(6),
+            },
+        )]
+        .into_iter()
+        .collect();
+        let required = vec![(1, true), (2, false), (3, false)].into_iter().collect();
+        let pack_files = pack_files(&required, files.data, &mods).unwrap();
+        let optionals: Vec<Option<&str>> = pack_files.iter().map(|file| file.optional.as_deref()).collect();
+        assert_eq!(optionals, vec![None, Some("JourneyMap"), Some("unknown.jar")]);
+    }
+
+    #[test]
+    fn files_without_download_url_are_reported() {
+        let files: ApiResponse<CurseForgeFile> = serde_json::from_str(
+            r#"{"data": [{"id": 1, "modId": 10, "fileName": "optifine.jar", "downloadUrl": null}]}"#,
+        )
+        .unwrap();
+        let required = vec![(1, true)].into_iter().collect();
+        let error = pack_files(&required, files.data, &HashMap::new()).err().unwrap().to_string();
+        assert!(error.contains("optifine.jar (project 10, file 1)"), "{}", error);
+    }
+}
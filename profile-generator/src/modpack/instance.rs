@@ -0,0 +1,200 @@
+use crate::modpack::{ModPack, Overrides, PackLoader};
+use launcher_extension_api::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Folders of the instance game directory copied into the profile.
+const INSTANCE_FOLDERS: [&str; 2] = ["mods", "config"];
+
+/// Components the launcher derives from Minecraft and the loader itself.
+const IMPLIED_COMPONENTS: [&str; 4] = [
+    "org.lwjgl",
+    "org.lwjgl3",
+    "net.fabricmc.intermediary",
+    "org.quiltmc.hashed",
+];
+
+/// `mmc-pack.json` of a MultiMC or Prism Launcher instance.
+#[derive(Deserialize, Serialize)]
+pub struct MmcPack {
+    pub components: Vec<MmcComponent>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MmcComponent {
+    pub uid: String,
+    pub version: Option<String>,
+    pub cached_version: Option<String>,
+}
+
+/// Reads an instance folder or its zip export. MultiMC keeps the game directory in
+/// `.minecraft`, Prism Launcher in `minecraft`.
+pub fn read_instance(path: &Path) -> Result<ModPack> {
+    if path.is_dir() {
+        let pack = fs::read(path.join("mmc-pack.json")).context("Can't find mmc-pack.json")?;
+        let config = fs::read_to_string(path.join("instance.cfg")).unwrap_or_default();
+        let game_folder = [".minecraft", "minecraft"]
+            .iter()
+            .map(|name| path.join(name))
+            .find(|folder| folder.is_dir());
+        let folders = game_folder
+            .map(|game_folder| {
+                INSTANCE_FOLDERS
+                    .iter()
+                    .map(|name| (game_folder.join(name), PathBuf::from(name)))
+                    .filter(|(folder, _)| folder.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        instance_pack(&pack, &config, Overrides::Folder { folders })
+    } else {
+        let mut zip = ZipArchive::new(File::open(path)?)?;
+        // Exports may wrap the instance in a folder named after it.
+        let root = zip
+            .file_names()
+            .filter(|name| name.rsplit('/').next() == Some("mmc-pack.json"))
+            .min_by_key(|name| name.len())
+            .map(|name| name.trim_end_matches("mmc-pack.json").to_string())
+            .context("Can't find mmc-pack.json")?;
+        let mut pack = Vec::new();
+        zip.by_name(&format!("{}mmc-pack.json", root))?.read_to_end(&mut pack)?;
+        let mut config = String::new();
+        if let Ok(mut file) = zip.by_name(&format!("{}instance.cfg", root)) {
+            file.read_to_string(&mut config)?;
+        }
+        let game_folder = [".minecraft", "minecraft"]
+            .iter()
+            .map(|name| format!("{}{}/", root, name))
+            .find(|folder| zip.file_names().any(|name| name.starts_with(folder.as_str())));
+        let prefixes = game_folder
+            .map(|game_folder| {
+                INSTANCE_FOLDERS
+                    .iter()
+                    .map(|name| (format!("{}{}", game_folder, name), PathBuf::from(name)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        instance_pack(
+            &pack,
+            &config,
+            Overrides::Archive {
+                path: path.to_path_buf(),
+                prefixes,
+            },
+        )
+    }
+}
+
+fn instance_pack(pack: &[u8], config: &str, overrides: Overrides) -> Result<ModPack> {
+    let pack: MmcPack = serde_json::from_slice(pack)?;
+    let mut game_version = None;
+    let mut loader = None;
+    for MmcComponent {
+        uid,
+        version,
+        cached_version,
+    } in pack.components
+    {
+        let version = match (version, cached_version) {
+            (Some(version), _) => version,
+            (None, Some(cached_version)) => {
+                log::warn!("Instance component {} has no version, using cached {}", uid, cached_version);
+                cached_version
+            }
+            (None, None) => return Err(launcher_extension_api::anyhow!("Instance component {} has no version", uid)),
+        };
+        match uid.as_str() {
+            "net.minecraft" => game_version = Some(version),
+            "net.minecraftforge" => loader = Some(PackLoader::Forge(version)),
+            "net.neoforged" => loader = Some(PackLoader::NeoForge(version)),
+            "net.fabricmc.fabric-loader" => loader = Some(PackLoader::Fabric(version)),
+            "org.quiltmc.quilt-loader" => loader = Some(PackLoader::Quilt(version)),
+            uid if IMPLIED_COMPONENTS.contains(&uid) => {}
+            uid => return Err(launcher_extension_api::anyhow!("Unsupported instance component {}", uid)),
+        }
+    }
+    let config = parse_config(config);
+    let jvm_args = match (config.get("OverrideJavaArgs"), config.get("JvmArgs")) {
+        (Some(&"true"), Some(args)) => split_args(args),
+        _ => vec![],
+    };
+    Ok(ModPack {
+        game_version: game_version.context("Instance does not contain net.minecraft")?,
+        loader,
+        files: vec![],
+        overrides: vec![overrides],
+        jvm_args,
+    })
+}
+
+/// `key=value` lines of `instance.cfg`, section headers are ignored.
+fn parse_config(config: &str) -> HashMap<&str, &str> {
+    config
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+/// Splits arguments the way MultiMC does, so quoted values such as `-Dfoo="a b"` stay one
+/// argument. Backslashes only escape inside quotes, Windows paths are kept as they are.
+fn split_args(args: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), '\\') => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                in_arg = true;
+                quote = Some(c);
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    result.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        result.push(current);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_args_keeps_quoted_values() {
+        assert_eq!(
+            split_args(r#"-Xmx4G  -Dfoo="a b" '-Dbar=c d' "-Dbaz=\"e\"" -Dpath=C:\java"#),
+            vec!["-Xmx4G", "-Dfoo=a b", "-Dbar=c d", "-Dbaz=\"e\"", r"-Dpath=C:\java"]
+        );
+    }
+
+    #[test]
+    fn split_args_keeps_empty_quotes() {
+        assert_eq!(split_args(r#"-Da="" -Db"#), vec!["-Da=", "-Db"]);
+        assert!(split_args("  ").is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use crate::modpack::{pack_path, ModPack, Overrides, PackFile, PackLoader};
+use launcher_extension_api::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// `modrinth.index.json` of a `.mrpack`.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModrinthIndex {
+    pub name: String,
+    pub files: Vec<ModrinthFile>,
+    pub dependencies: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModrinthFile {
+    pub path: String,
+    pub hashes: HashMap<String, String>,
+    pub env: Option<ModrinthEnv>,
+    pub downloads: Vec<String>,
+    pub file_size: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ModrinthEnv {
+    pub client: String,
+    pub server: String,
+}
+
+pub fn read_mrpack(path: &Path) -> Result<ModPack> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let index: ModrinthIndex = serde_json::from_reader(zip.by_name("modrinth.index.json")?)?;
+    let game_version = index
+        .dependencies
+        .get("minecraft")
+        .context("Modpack does not depend on minecraft")?
+        .clone();
+    let mut loader = None;
+    for (name, version) in &index.dependencies {
+        let version = version.clone();
+        let pack_loader = match name.as_str() {
+            "minecraft" => continue,
+            "forge" => PackLoader::Forge(version),
+            "neoforge" => PackLoader::NeoForge(version),
+            "fabric-loader" => PackLoader::Fabric(version),
+            "quilt-loader" => PackLoader::Quilt(version),
+            _ => return Err(launcher_extension_api::anyhow!("Unsupported modpack dependency {}", name)),
+        };
+        if loader.replace(pack_loader).is_some() {
+            return Err(launcher_extension_api::anyhow!("Modpack depends on more than one mod loader"));
+        }
+    }
+    let mut files = Vec::with_capacity(index.files.len());
+    for file in index.files {
+        let unsupported = file
+            .env
+            .as_ref()
+            .map(|env| env.client == "unsupported")
+            .unwrap_or(false);
+        if unsupported {
+            continue;
+        }
+        let path = pack_path(&file.path)?;
+        let mut downloads = file.downloads.into_iter();
+        let url = downloads
+            .next()
+            .with_context(|| format!("No download for {}", path.display()))?;
+        files.push(PackFile {
+            path,
+            url,
+            fallback_urls: downloads.collect(),
+            sha1: file.hashes.get("sha1").cloned(),
+            sha512: file.hashes.get("sha512").cloned(),
+            size: file.file_size,
+            optional: None,
+        });
+    }
+    Ok(ModPack {
+        game_version,
+        loader,
+        files,
+        overrides: vec![Overrides::Archive {
+            path: path.to_path_buf(),
+            prefixes: vec![
+                ("overrides".to_string(), PathBuf::new()),
+                ("client-overrides".to_string(), PathBuf::new()),
+            ],
+        }],
+        jvm_args: vec![],
+    })
+}
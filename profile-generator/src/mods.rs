@@ -0,0 +1,206 @@
+use crate::modpack::Overrides;
+use launcher_extension_api::launcher::optional::{Action, FileAction, Location, Optional, OptionalFiles};
+use launcher_extension_api::{Context, Result};
+use path_slash::PathBufExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Mods manifest, keys are paths relative to the mods folder. Mods it doesn't list
+/// are required.
+#[derive(Deserialize, Serialize, Default)]
+pub struct ModsManifest {
+    #[serde(default)]
+    pub mods: HashMap<String, ModEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ModEntry {
+    #[serde(default = "default_required")]
+    pub required: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Whether an optional mod starts enabled.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// Profile folder optional files are kept in, outside the folders the game reads.
+/// Enabling an optional renames its file into place.
+pub const OPTIONAL_FOLDER: &str = "optional";
+
+/// Mods copied into the `mods` folder of the profile, optional ones can be toggled
+/// by the player.
+pub struct ModsFolder {
+    folder: PathBuf,
+    /// Paths relative to the mods folder.
+    required_mods: Vec<String>,
+    optional_mods: Vec<OptionalMod>,
+}
+
+struct OptionalMod {
+    /// Path relative to the mods folder.
+    path: String,
+    name: String,
+    description: Option<String>,
+    enabled: bool,
+}
+
+impl ModsFolder {
+    pub fn read(folder: &Path, manifest: Option<&Path>) -> Result<Self> {
+        let mut manifest: ModsManifest = match manifest {
+            Some(path) => serde_json::from_reader(
+                File::open(path).with_context(|| format!("Can't open mods manifest {}", path.display()))?,
+            )?,
+            None => ModsManifest::default(),
+        };
+        let mut required_mods = Vec::new();
+        let mut optional_mods = Vec::new();
+        for entry in WalkDir::new(folder).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path().strip_prefix(folder)?.to_path_buf().to_slash_lossy();
+            let mod_entry = match manifest.mods.remove(&path) {
+                Some(mod_entry) if !mod_entry.required => mod_entry,
+                _ => {
+                    required_mods.push(path);
+                    continue;
+                }
+            };
+            let name = mod_entry.name.unwrap_or_else(|| {
+                entry
+                    .path()
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone())
+            });
+            optional_mods.push(OptionalMod {
+                path,
+                name,
+                description: mod_entry.description,
+                enabled: mod_entry.enabled,
+            });
+        }
+        if let Some(missing) = manifest.mods.keys().next() {
+            return Err(launcher_extension_api::anyhow!(
+                "Mods manifest lists {} which is not in {}",
+                missing,
+                folder.display()
+            ));
+        }
+        Ok(ModsFolder {
+            folder: folder.to_path_buf(),
+            required_mods,
+            optional_mods,
+        })
+    }
+
+    /// Required mods go to `mods`, optional ones to the optional folder.
+    pub fn overrides(&self) -> Overrides {
+        let required = self
+            .required_mods
+            .iter()
+            .map(|path| (PathBuf::from_slash(path), mods_path(path)));
+        let optional = self
+            .optional_mods
+            .iter()
+            .map(|optional_mod| {
+                let path = &optional_mod.path;
+                (PathBuf::from_slash(path), optional_path(&mods_path(path)))
+            });
+        Overrides::Files {
+            folder: self.folder.clone(),
+            files: required.chain(optional).collect(),
+        }
+    }
+
+    /// Visible optionals moving each optional mod into `mods`.
+    pub fn optionals(&self) -> Vec<Optional> {
+        self.optional_mods
+            .iter()
+            .map(|optional_mod| {
+                profile_optional(
+                    &mods_path(&optional_mod.path),
+                    &optional_mod.name,
+                    optional_mod.description.clone(),
+                    optional_mod.enabled,
+                )
+            })
+            .collect()
+    }
+}
+
+fn mods_path(path: &str) -> PathBuf {
+    Path::new("mods").join(PathBuf::from_slash(path))
+}
+
+/// Where the file of an optional for `path` is kept, both relative to the profile folder.
+pub fn optional_path(path: &Path) -> PathBuf {
+    Path::new(OPTIONAL_FOLDER).join(path)
+}
+
+/// Visible optional renaming its file from the optional folder to `path`, relative to
+/// the profile folder, when the player enables it.
+pub fn profile_optional(path: &Path, name: &str, description: Option<String>, enabled: bool) -> Optional {
+    let mut rename_paths = HashMap::new();
+    rename_paths.insert(optional_path(path), path.to_path_buf());
+    Optional {
+        actions: vec![Action::Files(FileAction {
+            location: Location::Profile,
+            files: OptionalFiles {
+                original_paths: vec![],
+                rename_paths,
+            },
+        })],
+        rules: vec![],
+        enabled,
+        visible: true,
+        description,
+        name: Some(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn optional_mods_stay_out_of_mods_folder() {
+        let root = std::env::temp_dir().join(format!("profilegen-mods-{}", std::process::id()));
+        let folder = root.join("mods");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("required.jar"), b"required").unwrap();
+        fs::write(folder.join("optional.jar"), b"optional").unwrap();
+        let manifest = root.join("mods.json");
+        fs::write(&manifest, r#"{"mods": {"optional.jar": {"required": false, "name": "Optional"}}}"#).unwrap();
+
+        let mods = ModsFolder::read(&folder, Some(&manifest)).unwrap();
+        let profile = root.join("profile");
+        mods.overrides().apply(&profile).unwrap();
+        let optionals = mods.optionals();
+
+        let exists = (
+            profile.join("mods").join("required.jar").is_file(),
+            profile.join("mods").join("optional.jar").exists(),
+            profile.join(OPTIONAL_FOLDER).join("mods").join("optional.jar").is_file(),
+        );
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(exists, (true, false, true));
+        assert_eq!(optionals.len(), 1);
+        assert!(matches!(
+            &optionals[0].actions[0],
+            Action::Files(action) if action.files.original_paths.is_empty()
+                && action.files.rename_paths.get(&Path::new(OPTIONAL_FOLDER).join("mods").join("optional.jar"))
+                    == Some(&Path::new("mods").join("optional.jar"))
+        ));
+    }
+}
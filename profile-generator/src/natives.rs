@@ -0,0 +1,490 @@
+use crate::artifact::Artifact;
+use crate::minecraft::libraries::{File as LibraryFile, Library, Rule};
+use crate::rules::{self, Target};
+use launcher_extension_api::launcher::optional::{Action, CompareMode, FileAction, Location, Optional, OptionalFiles};
+use launcher_extension_api::launcher::validation::OsType;
+use launcher_extension_api::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{create_dir_all, remove_dir_all, rename, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+use path_slash::PathBufExt;
+
+/// Platforms the legacy `natives` section of a library can describe.
+const LEGACY_PLATFORMS: [NativePlatform; 5] = [
+    NativePlatform::LinuxX64,
+    NativePlatform::LinuxX86,
+    NativePlatform::WindowsX64,
+    NativePlatform::WindowsX86,
+    NativePlatform::MacOsX64,
+];
+
+/// Natives are extracted into one folder per platform, so every client only receives
+/// binaries it can load.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NativePlatform {
+    LinuxX64,
+    LinuxX86,
+    LinuxArm64,
+    WindowsX64,
+    WindowsX86,
+    WindowsArm64,
+    MacOsX64,
+    MacOsArm64,
+}
+
+impl NativePlatform {
+    pub fn folder(&self) -> &'static str {
+        match self {
+            NativePlatform::LinuxX64 => "linux-x64",
+            NativePlatform::LinuxX86 => "linux-x86",
+            NativePlatform::LinuxArm64 => "linux-arm64",
+            NativePlatform::WindowsX64 => "windows-x64",
+            NativePlatform::WindowsX86 => "windows-x86",
+            NativePlatform::WindowsArm64 => "windows-arm64",
+            NativePlatform::MacOsX64 => "macos-x64",
+            NativePlatform::MacOsArm64 => "macos-arm64",
+        }
+    }
+
+    pub fn target(&self) -> Target {
+        match self {
+            NativePlatform::LinuxX64 => rules::LINUX_X64,
+            NativePlatform::LinuxX86 => rules::LINUX_X86,
+            NativePlatform::LinuxArm64 => rules::LINUX_ARM64,
+            NativePlatform::WindowsX64 => rules::WINDOWS_X64,
+            NativePlatform::WindowsX86 => rules::WINDOWS_X86,
+            NativePlatform::WindowsArm64 => rules::WINDOWS_ARM64,
+            NativePlatform::MacOsX64 => rules::MACOS_X64,
+            NativePlatform::MacOsArm64 => rules::MACOS_ARM64,
+        }
+    }
+
+    /// Value of `${arch}` in natives classifiers.
+    fn arch_bits(&self) -> &'static str {
+        match self {
+            NativePlatform::WindowsX86 | NativePlatform::LinuxX86 => "32",
+            _ => "64",
+        }
+    }
+
+    /// Launcher platform served by this folder. The launcher has no ARM platforms yet,
+    /// their natives are extracted but not delivered.
+    pub fn os_type(&self) -> Option<OsType> {
+        match self {
+            NativePlatform::LinuxX64 => Some(OsType::LinuxX64),
+            NativePlatform::LinuxX86 => Some(OsType::LinuxX32),
+            NativePlatform::WindowsX64 => Some(OsType::WindowsX64),
+            NativePlatform::WindowsX86 => Some(OsType::WindowsX32),
+            NativePlatform::MacOsX64 => Some(OsType::MacOsX64),
+            NativePlatform::LinuxArm64 | NativePlatform::WindowsArm64 | NativePlatform::MacOsArm64 => None,
+        }
+    }
+
+    /// Platforms of a 1.19+ natives library like `org.lwjgl:lwjgl:3.3.1:natives-macos-arm64`.
+    /// `natives-windows` also serves 32-bit Windows, which prefers a dedicated jar when there
+    /// is one. LWJGL 3 only ships 64-bit Linux binaries in `natives-linux`, and unknown
+    /// classifiers like `natives-linux-arm32` are not planned at all.
+    fn from_classifier(classifier: &str) -> &'static [NativePlatform] {
+        match classifier {
+            "natives-linux" => &[NativePlatform::LinuxX64],
+            "natives-linux-arm64" => &[NativePlatform::LinuxArm64],
+            "natives-windows" => &[NativePlatform::WindowsX64, NativePlatform::WindowsX86],
+            "natives-windows-x86" => &[NativePlatform::WindowsX86],
+            "natives-windows-arm64" => &[NativePlatform::WindowsArm64],
+            "natives-macos" | "natives-osx" => &[NativePlatform::MacOsX64],
+            "natives-macos-arm64" => &[NativePlatform::MacOsArm64],
+            _ => &[],
+        }
+    }
+}
+
+/// Downloaded natives jar, the platforms its binaries are extracted for and the entry
+/// prefixes from `extract.exclude` that are left out.
+pub struct NativeJar {
+    pub path: PathBuf,
+    pub platforms: Vec<NativePlatform>,
+    pub exclude: Vec<String>,
+}
+
+/// Legacy natives jars of one library, filtered by the library rules. The `natives` section
+/// maps the OS to a classifier, libraries without it use the `natives-<os>` convention.
+/// LWJGL 2 packs both Windows architectures into a single jar.
+pub fn classifier_natives(library: &Library) -> Vec<(&LibraryFile, Vec<NativePlatform>)> {
+    let classifiers = match &library.downloads.classifiers {
+        Some(classifiers) => classifiers,
+        None => return vec![],
+    };
+    let mut jars: Vec<(&LibraryFile, Vec<NativePlatform>)> = Vec::new();
+    for platform in LEGACY_PLATFORMS.iter() {
+        if !is_allowed(*platform, library.rules.as_deref()) {
+            continue;
+        }
+        let os_name = platform.target().os_name;
+        let classifier = match &library.natives {
+            Some(natives) => match natives.get(os_name) {
+                Some(classifier) => classifier.replace("${arch}", platform.arch_bits()),
+                None => continue,
+            },
+            None => format!("natives-{}", os_name),
+        };
+        if let Some(file) = classifiers.get(&classifier) {
+            match jars.iter_mut().find(|(known, _)| known.url == file.url) {
+                Some((_, platforms)) => platforms.push(*platform),
+                None => jars.push((file, vec![*platform])),
+            }
+        }
+    }
+    jars
+}
+
+fn is_allowed(platform: NativePlatform, rules: Option<&[Rule]>) -> bool {
+    rules
+        .map(|rules| rules::is_allowed(rules, &platform.target(), &HashMap::new()))
+        .unwrap_or(true)
+}
+
+/// Whether the library is a 1.19+ natives jar, these are listed as separate libraries
+/// with the platform in the classifier and must not end up on the classpath.
+pub fn is_native_library(name: &str) -> bool {
+    name.parse::<Artifact>()
+        .ok()
+        .and_then(|artifact| artifact.classifier)
+        .map(|classifier| classifier.starts_with("natives-"))
+        .unwrap_or(false)
+}
+
+/// Platforms of a 1.19+ natives library the launcher can use and its rules allow.
+pub fn library_native_platforms(name: &str, rules: Option<&[Rule]>) -> Vec<NativePlatform> {
+    let classifier = match name.parse::<Artifact>().ok().and_then(|artifact| artifact.classifier) {
+        Some(classifier) => classifier,
+        None => return vec![],
+    };
+    NativePlatform::from_classifier(&classifier)
+        .iter()
+        .copied()
+        .filter(|platform| is_allowed(*platform, rules))
+        .collect()
+}
+
+/// Extracts every jar into `<native_folder>/<platform>` and returns the optionals that
+/// deliver each platform folder to the matching clients only. Entries keep their path
+/// inside the platform folder and are delivered under their file name, the way the game
+/// looks them up on `java.library.path`.
+///
+/// All profiles of a version share the natives folder, so the new natives are extracted
+/// next to it and only replace it once extraction succeeded.
+pub fn extract_natives(jars: &[NativeJar], native_folder: &Path) -> Result<Vec<Optional>> {
+    let mut staging_name = native_folder.file_name().unwrap_or_default().to_os_string();
+    staging_name.push(".new");
+    let staging = native_folder.with_file_name(staging_name);
+    if staging.exists() {
+        remove_dir_all(&staging)?;
+    }
+    let optionals = extract_into(jars, &staging)?;
+    if native_folder.exists() {
+        remove_dir_all(native_folder)?;
+    }
+    if staging.exists() {
+        rename(&staging, native_folder)?;
+    } else {
+        create_dir_all(native_folder)?;
+    }
+    Ok(optionals)
+}
+
+fn extract_into(jars: &[NativeJar], native_folder: &Path) -> Result<Vec<Optional>> {
+    let mut extracted: BTreeMap<NativePlatform, BTreeMap<String, String>> = BTreeMap::new();
+    // Jars for fewer platforms go first, so a dedicated 32-bit jar wins over a shared one.
+    let mut jars: Vec<&NativeJar> = jars.iter().collect();
+    jars.sort_by_key(|jar| jar.platforms.len());
+    for jar in jars {
+        let mut zip = ZipArchive::new(File::open(&jar.path)?)?;
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index)?;
+            if !file.is_file() {
+                continue;
+            }
+            let path = match file.enclosed_name() {
+                Some(path) => path.to_path_buf(),
+                None => {
+                    log::warn!("Skip natives entry {} of {} outside of the jar", file.name(), jar.path.display());
+                    continue;
+                }
+            };
+            let name = path.to_slash_lossy();
+            if is_excluded(&name, &jar.exclude) {
+                continue;
+            }
+            let file_name = match path.file_name() {
+                Some(file_name) => file_name.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+            for platform in &jar.platforms {
+                let files = extracted.entry(*platform).or_default();
+                if files.contains_key(&file_name) {
+                    if jar.platforms.len() > 1 {
+                        continue;
+                    }
+                    log::warn!(
+                        "Skip natives entry {} of {}, {} already has {}",
+                        name,
+                        jar.path.display(),
+                        platform.folder(),
+                        file_name
+                    );
+                    continue;
+                }
+                let target = native_folder.join(platform.folder()).join(&path);
+                if let Some(parent) = target.parent() {
+                    create_dir_all(parent)?;
+                }
+                File::create(&target)?.write_all(&content)?;
+                files.insert(file_name.clone(), name.clone());
+            }
+        }
+    }
+    let mut optionals = Vec::new();
+    for (platform, files) in extracted {
+        let os_type = match platform.os_type() {
+            Some(os_type) => os_type,
+            None => continue,
+        };
+        let platform_path = |name: &str| PathBuf::from(format!("{}/{}", platform.folder(), name));
+        let original_paths: Vec<PathBuf> = files.values().map(|name| platform_path(name)).collect();
+        let rename_paths = files
+            .iter()
+            .map(|(file_name, name)| (platform_path(name), PathBuf::from(file_name)))
+            .collect();
+        optionals.push(Optional {
+            actions: vec![Action::Files(FileAction {
+                location: Location::Natives,
+                files: OptionalFiles {
+                    original_paths,
+                    rename_paths,
+                },
+            })],
+            rules: vec![rules::os_rule(os_type, CompareMode::Equal)],
+            enabled: true,
+            visible: false,
+            description: None,
+            name: None,
+        });
+    }
+    Ok(optionals)
+}
+
+/// `META-INF` is never extracted, vanilla versions list it in `extract.exclude` anyway
+/// but 1.19+ natives libraries have no `extract` section.
+fn is_excluded(name: &str, exclude: &[String]) -> bool {
+    name.starts_with("META-INF/") || exclude.iter().any(|prefix| name.starts_with(prefix.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::ZipWriter;
+
+    fn classifier_file(classifier: &str) -> String {
+        format!(
+            r#""{}": {{"url": "https://libraries.minecraft.net/lwjgl-{}.jar", "sha1": null, "size": 1}}"#,
+            classifier, classifier
+        )
+    }
+
+    #[test]
+    fn arch_is_substituted_per_windows_platform() {
+        let library: Library = serde_json::from_str(&format!(
+            r#"{{
+                "name": "tv.twitch:twitch-platform:5.16",
+                "natives": {{"linux": "natives-linux", "windows": "natives-windows-${{arch}}", "osx": "natives-osx"}},
+                "downloads": {{"classifiers": {{{}, {}, {}, {}}}}}
+            }}"#,
+            classifier_file("natives-linux"),
+            classifier_file("natives-windows-32"),
+            classifier_file("natives-windows-64"),
+            classifier_file("natives-osx")
+        ))
+        .unwrap();
+        let jars = classifier_natives(&library);
+        let platforms_of = |suffix: &str| {
+            jars.iter()
+                .find(|(file, _)| file.url.ends_with(suffix))
+                .map(|(_, platforms)| platforms.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(platforms_of("natives-windows-64.jar"), vec![NativePlatform::WindowsX64]);
+        assert_eq!(platforms_of("natives-windows-32.jar"), vec![NativePlatform::WindowsX86]);
+        assert_eq!(
+            platforms_of("natives-linux.jar"),
+            vec![NativePlatform::LinuxX64, NativePlatform::LinuxX86]
+        );
+        assert_eq!(platforms_of("natives-osx.jar"), vec![NativePlatform::MacOsX64]);
+    }
+
+    #[test]
+    fn one_jar_serves_both_windows_architectures() {
+        let library: Library = serde_json::from_str(&format!(
+            r#"{{
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+                "natives": {{"windows": "natives-windows"}},
+                "downloads": {{"classifiers": {{{}}}}}
+            }}"#,
+            classifier_file("natives-windows")
+        ))
+        .unwrap();
+        let jars = classifier_natives(&library);
+        assert_eq!(jars.len(), 1);
+        assert_eq!(jars[0].1, vec![NativePlatform::WindowsX64, NativePlatform::WindowsX86]);
+    }
+
+    #[test]
+    fn missing_natives_section_uses_os_name() {
+        let library: Library = serde_json::from_str(&format!(
+            r#"{{
+                "name": "org.lwjgl:lwjgl:3.2.2",
+                "rules": [{{"action": "allow"}}, {{"action": "disallow", "os": {{"name": "linux"}}}}],
+                "downloads": {{"classifiers": {{{}, {}}}}}
+            }}"#,
+            classifier_file("natives-linux"),
+            classifier_file("natives-osx")
+        ))
+        .unwrap();
+        let jars = classifier_natives(&library);
+        assert_eq!(jars.len(), 1);
+        assert_eq!(jars[0].1, vec![NativePlatform::MacOsX64]);
+    }
+
+    #[test]
+    fn extraction_replaces_the_folder_of_the_version() {
+        let root = std::env::temp_dir().join(format!("profilegen-natives-{}", std::process::id()));
+        let _ = remove_dir_all(&root);
+        create_dir_all(&root).unwrap();
+        let jar_path = root.join("natives-linux.jar");
+        let mut jar = ZipWriter::new(File::create(&jar_path).unwrap());
+        jar.start_file("META-INF/MANIFEST.MF", Default::default()).unwrap();
+        jar.write_all(b"Manifest-Version: 1.0").unwrap();
+        jar.start_file("liblwjgl.so", Default::default()).unwrap();
+        jar.write_all(b"native").unwrap();
+        jar.finish().unwrap();
+        let native_folder = root.join("natives").join("1.12.2");
+        create_dir_all(native_folder.join("stale")).unwrap();
+        let jars = [NativeJar {
+            path: jar_path,
+            platforms: vec![NativePlatform::LinuxX64, NativePlatform::LinuxX86],
+            exclude: vec![],
+        }];
+        let optionals = extract_natives(&jars, &native_folder).unwrap();
+        assert_eq!(optionals.len(), 2);
+        assert!(native_folder.join("linux-x86").join("liblwjgl.so").is_file());
+        assert!(!native_folder.join("linux-x64").join("META-INF").exists());
+        assert!(!native_folder.join("stale").exists());
+        assert!(!root.join("natives").join("1.12.2.new").exists());
+        remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn modern_classifiers_map_to_platforms() {
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-windows", None),
+            vec![NativePlatform::WindowsX64, NativePlatform::WindowsX86]
+        );
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-windows-x86", None),
+            vec![NativePlatform::WindowsX86]
+        );
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-macos", None),
+            vec![NativePlatform::MacOsX64]
+        );
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-linux", None),
+            vec![NativePlatform::LinuxX64]
+        );
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-macos-arm64", None),
+            vec![NativePlatform::MacOsArm64]
+        );
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-windows-arm64", None),
+            vec![NativePlatform::WindowsArm64]
+        );
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-linux-arm64", None),
+            vec![NativePlatform::LinuxArm64]
+        );
+        assert!(library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-linux-arm32", None).is_empty());
+        assert!(library_native_platforms("org.lwjgl:lwjgl:3.3.1", None).is_empty());
+    }
+
+    #[test]
+    fn modern_classifiers_respect_rules() {
+        let rules: Vec<Rule> = serde_json::from_str(r#"[{"action": "allow", "os": {"name": "windows", "arch": "x86"}}]"#).unwrap();
+        assert_eq!(
+            library_native_platforms("org.lwjgl:lwjgl:3.3.1:natives-windows", Some(&rules)),
+            vec![NativePlatform::WindowsX86]
+        );
+    }
+
+    #[test]
+    fn dedicated_jar_wins_for_its_platform() {
+        let root = std::env::temp_dir().join(format!("profilegen-natives-x86-{}", std::process::id()));
+        let _ = remove_dir_all(&root);
+        create_dir_all(&root).unwrap();
+        let write_jar = |name: &str, content: &[u8]| {
+            let path = root.join(name);
+            let mut jar = ZipWriter::new(File::create(&path).unwrap());
+            jar.start_file("windows/x64/org/lwjgl/lwjgl.dll", Default::default()).unwrap();
+            jar.write_all(content).unwrap();
+            jar.finish().unwrap();
+            path
+        };
+        let jars = [
+            NativeJar {
+                path: write_jar("natives-windows.jar", b"x64"),
+                platforms: vec![NativePlatform::WindowsX64, NativePlatform::WindowsX86],
+                exclude: vec![],
+            },
+            NativeJar {
+                path: write_jar("natives-windows-x86.jar", b"x86"),
+                platforms: vec![NativePlatform::WindowsX86],
+                exclude: vec![],
+            },
+        ];
+        let native_folder = root.join("natives");
+        extract_natives(&jars, &native_folder).unwrap();
+        let read = |platform: &str| std::fs::read(native_folder.join(platform).join("windows/x64/org/lwjgl/lwjgl.dll")).unwrap();
+        assert_eq!(read("windows-x86"), b"x86");
+        assert_eq!(read("windows-x64"), b"x64");
+        remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn arm_natives_are_extracted_but_not_delivered() {
+        let root = std::env::temp_dir().join(format!("profilegen-natives-arm-{}", std::process::id()));
+        let _ = remove_dir_all(&root);
+        create_dir_all(&root).unwrap();
+        let jar_path = root.join("natives-macos-arm64.jar");
+        let mut jar = ZipWriter::new(File::create(&jar_path).unwrap());
+        jar.start_file("macos/arm64/org/lwjgl/liblwjgl.dylib", Default::default()).unwrap();
+        jar.write_all(b"arm64").unwrap();
+        jar.finish().unwrap();
+        let jars = [NativeJar {
+            path: jar_path,
+            platforms: vec![NativePlatform::MacOsArm64],
+            exclude: vec![],
+        }];
+        let native_folder = root.join("natives");
+        let optionals = extract_natives(&jars, &native_folder).unwrap();
+        let extracted = native_folder
+            .join("macos-arm64")
+            .join("macos/arm64/org/lwjgl/liblwjgl.dylib")
+            .is_file();
+        remove_dir_all(&root).unwrap();
+        assert!(extracted);
+        assert!(optionals.is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::artifact::Artifact;
+use crate::minecraft::installer::{ForgeInstaller, Processor};
+use crate::progress::Progress;
+use crate::util::file_sha1;
+use launcher_extension_api::{Context, Result};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs::{copy, create_dir_all, remove_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use zip::ZipArchive;
+
+const CLASSPATH_SEPARATOR: &str = if cfg!(windows) { ";" } else { ":" };
+
+/// Runs the client processors of a Forge installer in a maven layout work folder and
+/// copies the patched artifacts into the libraries folder.
+pub struct InstallerPlan {
+    installer: ForgeInstaller,
+    java: PathBuf,
+    work_folder: PathBuf,
+    minecraft_jar: PathBuf,
+    libraries_folder: PathBuf,
+    /// Artifacts without a download url, shipped inside the installer under `maven/`.
+    bundled: Vec<(String, PathBuf)>,
+    /// Artifacts without a download url the processors create.
+    generated: Vec<PathBuf>,
+}
+
+impl InstallerPlan {
+    pub fn new(
+        installer: ForgeInstaller,
+        java: PathBuf,
+        work_folder: PathBuf,
+        minecraft_jar: PathBuf,
+        libraries_folder: PathBuf,
+        bundled: Vec<(String, PathBuf)>,
+        generated: Vec<PathBuf>,
+    ) -> Self {
+        InstallerPlan {
+            installer,
+            java,
+            work_folder,
+            minecraft_jar,
+            libraries_folder,
+            bundled,
+            generated,
+        }
+    }
+
+    /// Maven layout folder the processors read their libraries from.
+    pub fn work_libraries(work_folder: &Path) -> PathBuf {
+        work_folder.join("libraries")
+    }
+
+    pub fn processor_count(&self) -> usize {
+        self.client_processors().count()
+    }
+
+    fn client_processors(&self) -> impl Iterator<Item = &Processor> {
+        self.installer.profile.processors.iter().filter(|processor| processor.is_client())
+    }
+
+    /// Prints the files the installer writes, the artifacts shipped in the installer and
+    /// what each processor produces.
+    pub fn print(&self) -> Result<()> {
+        println!("Forge installer runs {} processors after download", self.processor_count());
+        for (entry, path) in &self.bundled {
+            println!("  maven/{} -> {}", entry, path.display());
+        }
+        for path in &self.generated {
+            println!("  created by processors: {}", path.display());
+        }
+        let data = self.data(&self.work_folder.join("installer.jar"), false)?;
+        let work_libraries = InstallerPlan::work_libraries(&self.work_folder);
+        for processor in self.client_processors() {
+            for (path, _) in self.outputs(processor, &data)? {
+                let target = match path.strip_prefix(&work_libraries) {
+                    Ok(relative) => self.libraries_folder.join(relative),
+                    Err(_) => path,
+                };
+                println!("  {} -> {}", processor.jar, target.display());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(&self, progress: &Progress) -> Result<()> {
+        let _work_folder = RemoveOnDrop(&self.work_folder);
+        for (entry, path) in &self.bundled {
+            write_file(path, &self.installer.read_file(&format!("maven/{}", entry))?)?;
+        }
+        let installer_path = self.work_folder.join("installer.jar");
+        write_file(&installer_path, &self.installer.jar)?;
+        let data = self.data(&installer_path, true)?;
+        let total = self.processor_count();
+        for (index, processor) in self.client_processors().enumerate() {
+            progress.message(&format!("Run processor {}/{} {}", index + 1, total, processor.jar));
+            let outputs = self.outputs(processor, &data)?;
+            if !outputs.is_empty() && outputs_valid(&outputs)? {
+                continue;
+            }
+            self.run_processor(processor, &data)?;
+            for (path, expected) in &outputs {
+                let actual = file_sha1(path)
+                    .with_context(|| format!("Processor {} did not create {}", processor.jar, path.display()))?;
+                if !actual.eq_ignore_ascii_case(expected) {
+                    return Err(launcher_extension_api::anyhow!(
+                        "Checksum mismatch for {} created by {}: expected {}, got {}",
+                        path.display(),
+                        processor.jar,
+                        expected,
+                        actual
+                    ));
+                }
+            }
+        }
+        self.copy_artifacts(&data)?;
+        for path in &self.generated {
+            if !path.is_file() {
+                return Err(launcher_extension_api::anyhow!(
+                    "Forge installer neither ships nor creates {}",
+                    path.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Client values of `install_profile.json` data plus the entries the installer itself
+    /// provides. Artifacts resolve to their path in the work folder, files are extracted
+    /// when `extract` is set.
+    fn data(&self, installer_path: &Path, extract: bool) -> Result<HashMap<String, String>> {
+        let mut data = HashMap::new();
+        for (key, value) in &self.installer.profile.data {
+            let value = &value.client;
+            let resolved = if let Some(literal) = strip(value, '\'', '\'') {
+                literal.to_string()
+            } else if value.starts_with('[') {
+                path_string(&self.artifact_path(value)?)
+            } else if value.starts_with('/') {
+                let path = self.work_folder.join(value.trim_start_matches('/'));
+                if extract {
+                    write_file(&path, &self.installer.read_file(value)?)?;
+                }
+                path_string(&path)
+            } else {
+                value.clone()
+            };
+            data.insert(key.clone(), resolved);
+        }
+        data.insert("SIDE".to_string(), "client".to_string());
+        data.insert("MINECRAFT_JAR".to_string(), path_string(&self.minecraft_jar));
+        data.insert("MINECRAFT_VERSION".to_string(), self.installer.profile.minecraft.clone());
+        data.insert("ROOT".to_string(), path_string(&self.work_folder));
+        data.insert("INSTALLER".to_string(), path_string(installer_path));
+        data.insert(
+            "LIBRARY_DIR".to_string(),
+            path_string(&InstallerPlan::work_libraries(&self.work_folder)),
+        );
+        Ok(data)
+    }
+
+    /// `[group:artifact:version]` in the work folder.
+    fn artifact_path(&self, value: &str) -> Result<PathBuf> {
+        let name = strip(value, '[', ']').unwrap_or(value);
+        let artifact: Artifact = name
+            .parse()
+            .map_err(|_| launcher_extension_api::anyhow!("Invalid artifact {}", name))?;
+        Ok(InstallerPlan::work_libraries(&self.work_folder).join(artifact.to_path()))
+    }
+
+    fn substitute(&self, value: &str, data: &HashMap<String, String>) -> Result<String> {
+        if value.starts_with('[') {
+            return Ok(path_string(&self.artifact_path(value)?));
+        }
+        let regex = Regex::new(r"\{(\w+)\}").unwrap();
+        let mut missing = None;
+        let substituted = regex.replace_all(value, |captures: &Captures| match data.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => {
+                missing = Some(captures[1].to_string());
+                captures[0].to_string()
+            }
+        });
+        match missing {
+            Some(key) => Err(launcher_extension_api::anyhow!("Unknown installer data {}", key)),
+            None => Ok(strip(&substituted, '\'', '\'').unwrap_or(&substituted).to_string()),
+        }
+    }
+
+    fn outputs(&self, processor: &Processor, data: &HashMap<String, String>) -> Result<Vec<(PathBuf, String)>> {
+        processor
+            .outputs
+            .iter()
+            .map(|(path, sha1)| Ok((PathBuf::from(self.substitute(path, data)?), self.substitute(sha1, data)?)))
+            .collect()
+    }
+
+    fn run_processor(&self, processor: &Processor, data: &HashMap<String, String>) -> Result<()> {
+        let jar = self.artifact_path(&processor.jar)?;
+        let mut classpath = vec![path_string(&jar)];
+        for library in &processor.classpath {
+            classpath.push(path_string(&self.artifact_path(library)?));
+        }
+        let args = processor
+            .args
+            .iter()
+            .map(|arg| self.substitute(arg, data))
+            .collect::<Result<Vec<String>>>()?;
+        let output = Command::new(&self.java)
+            .arg("-cp")
+            .arg(classpath.join(CLASSPATH_SEPARATOR))
+            .arg(main_class(&jar)?)
+            .args(&args)
+            .output()
+            .with_context(|| format!("Can't run {}", self.java.display()))?;
+        if !output.status.success() {
+            return Err(launcher_extension_api::anyhow!(
+                "Processor {} failed with {}:\n{}",
+                processor.jar,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copies every artifact the processors produced and the game loads at runtime,
+    /// like the patched client jar, into the libraries folder.
+    fn copy_artifacts(&self, data: &HashMap<String, String>) -> Result<()> {
+        let work_libraries = InstallerPlan::work_libraries(&self.work_folder);
+        let mut artifacts: Vec<PathBuf> = data.values().map(PathBuf::from).collect();
+        for processor in self.client_processors() {
+            for (path, _) in self.outputs(processor, data)? {
+                artifacts.push(path);
+            }
+        }
+        for artifact in artifacts {
+            if let Ok(relative) = artifact.strip_prefix(&work_libraries) {
+                if artifact.is_file() {
+                    let target = self.libraries_folder.join(relative);
+                    if let Some(parent) = target.parent() {
+                        create_dir_all(parent)?;
+                    }
+                    copy(&artifact, target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes the work folder however the installer run ends.
+struct RemoveOnDrop<'a>(&'a Path);
+
+impl Drop for RemoveOnDrop<'_> {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            if let Err(e) = remove_dir_all(self.0) {
+                log::warn!("Can't remove {}: {}", self.0.display(), e);
+            }
+        }
+    }
+}
+
+fn outputs_valid(outputs: &[(PathBuf, String)]) -> Result<bool> {
+    for (path, expected) in outputs {
+        if !path.is_file() || !file_sha1(path)?.eq_ignore_ascii_case(expected) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// `Main-Class` from the jar manifest.
+fn main_class(jar: &Path) -> Result<String> {
+    let mut zip = ZipArchive::new(File::open(jar)?)?;
+    let mut manifest = String::new();
+    zip.by_name("META-INF/MANIFEST.MF")?.read_to_string(&mut manifest)?;
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("Main-Class:"))
+        .map(|main_class| main_class.trim().to_string())
+        .with_context(|| format!("Can't find main class of {}", jar.display()))
+}
+
+fn strip(value: &str, prefix: char, suffix: char) -> Option<&str> {
+    value.strip_prefix(prefix).and_then(|value| value.strip_suffix(suffix))
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    File::create(path)?.write_all(content)?;
+    Ok(())
+}
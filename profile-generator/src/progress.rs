@@ -0,0 +1,216 @@
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use launcher_extension_api::Error;
+
+const CONSOLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Assets,
+    Client,
+    Libraries,
+    Natives,
+    Loader,
+    Mods,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::Assets => "assets",
+            Phase::Client => "client",
+            Phase::Libraries => "libraries",
+            Phase::Natives => "natives",
+            Phase::Loader => "loader",
+            Phase::Mods => "mods",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressEvent {
+    Start,
+    Progress,
+    Finish,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProgressState {
+    pub event: ProgressEvent,
+    pub phase: Phase,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct ProgressMessage<'a> {
+    event: &'static str,
+    message: &'a str,
+}
+
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, state: &ProgressState);
+
+    fn message(&self, message: &str);
+}
+
+/// Human readable progress, printed at most once per second while a phase is running.
+pub struct ConsoleReporter {
+    last_report: Mutex<Instant>,
+}
+
+impl Default for ConsoleReporter {
+    fn default() -> Self {
+        ConsoleReporter {
+            last_report: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl ProgressReporter for ConsoleReporter {
+    fn report(&self, state: &ProgressState) {
+        if let Ok(mut last_report) = self.last_report.lock() {
+            if state.event == ProgressEvent::Progress && last_report.elapsed() < CONSOLE_INTERVAL {
+                return;
+            }
+            *last_report = Instant::now();
+        }
+        match state.event {
+            ProgressEvent::Start => println!("Download {} ({} files)...", state.phase, state.files_total),
+            _ => println!(
+                "[{}] {}/{} files, {:.1}/{:.1} MiB",
+                state.phase,
+                state.files_done,
+                state.files_total,
+                to_mib(state.bytes_done),
+                to_mib(state.bytes_total)
+            ),
+        }
+    }
+
+    fn message(&self, message: &str) {
+        println!("{}", message);
+    }
+}
+
+fn to_mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Machine readable progress, one JSON object per line.
+pub struct JsonReporter;
+
+impl ProgressReporter for JsonReporter {
+    fn report(&self, state: &ProgressState) {
+        if let Ok(line) = serde_json::to_string(state) {
+            println!("{}", line);
+        }
+    }
+
+    fn message(&self, message: &str) {
+        if let Ok(line) = serde_json::to_string(&ProgressMessage { event: "message", message }) {
+            println!("{}", line);
+        }
+    }
+}
+
+pub enum ProgressFormat {
+    Console,
+    Json,
+}
+
+impl ProgressFormat {
+    pub fn reporter(&self) -> Box<dyn ProgressReporter> {
+        match self {
+            ProgressFormat::Console => Box::new(ConsoleReporter::default()),
+            ProgressFormat::Json => Box::new(JsonReporter),
+        }
+    }
+}
+
+impl FromStr for ProgressFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "console" => Ok(ProgressFormat::Console),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(launcher_extension_api::anyhow!("Unknown progress format: {}", s)),
+        }
+    }
+}
+
+pub struct Progress {
+    reporter: Box<dyn ProgressReporter>,
+    phase: Mutex<Phase>,
+    files_done: AtomicU64,
+    files_total: AtomicU64,
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+}
+
+impl Progress {
+    pub fn new(reporter: Box<dyn ProgressReporter>) -> Self {
+        Progress {
+            reporter,
+            phase: Mutex::new(Phase::Assets),
+            files_done: AtomicU64::new(0),
+            files_total: AtomicU64::new(0),
+            bytes_done: AtomicU64::new(0),
+            bytes_total: AtomicU64::new(0),
+        }
+    }
+
+    pub fn start_phase(&self, phase: Phase, files_total: u64, bytes_total: u64) {
+        if let Ok(mut current) = self.phase.lock() {
+            *current = phase;
+        }
+        self.files_done.store(0, Ordering::SeqCst);
+        self.files_total.store(files_total, Ordering::SeqCst);
+        self.bytes_done.store(0, Ordering::SeqCst);
+        self.bytes_total.store(bytes_total, Ordering::SeqCst);
+        self.report(ProgressEvent::Start);
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::SeqCst);
+    }
+
+    pub fn sub_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_sub(bytes, Ordering::SeqCst);
+    }
+
+    pub fn file_done(&self) {
+        self.files_done.fetch_add(1, Ordering::SeqCst);
+        self.report(ProgressEvent::Progress);
+    }
+
+    pub fn finish_phase(&self) {
+        self.report(ProgressEvent::Finish);
+    }
+
+    pub fn message(&self, message: &str) {
+        self.reporter.message(message);
+    }
+
+    fn report(&self, event: ProgressEvent) {
+        let phase = self.phase.lock().map(|phase| *phase).unwrap_or(Phase::Assets);
+        self.reporter.report(&ProgressState {
+            event,
+            phase,
+            files_done: self.files_done.load(Ordering::SeqCst),
+            files_total: self.files_total.load(Ordering::SeqCst),
+            bytes_done: self.bytes_done.load(Ordering::SeqCst),
+            bytes_total: self.bytes_total.load(Ordering::SeqCst),
+        });
+    }
+}
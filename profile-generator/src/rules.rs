@@ -0,0 +1,209 @@
+use crate::minecraft::libraries::{Rule, RuleAction};
+use launcher_extension_api::launcher::optional::{CompareMode, OsRule, Rule as LauncherRule};
+use launcher_extension_api::launcher::validation::OsType;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Platform as Mojang rules see it. The exact OS version is only known on the client,
+/// so `os_version` is a representative current release used to evaluate `os.version` patterns.
+pub struct Target {
+    pub os_name: &'static str,
+    pub os_version: &'static str,
+    pub arch: &'static str,
+}
+
+pub const LINUX_X64: Target = Target { os_name: "linux", os_version: "6.0", arch: "x86_64" };
+pub const LINUX_X86: Target = Target { os_name: "linux", os_version: "6.0", arch: "x86" };
+pub const MACOS_X64: Target = Target { os_name: "osx", os_version: "13.0", arch: "x86_64" };
+pub const WINDOWS_X64: Target = Target { os_name: "windows", os_version: "10.0", arch: "x86_64" };
+pub const WINDOWS_X86: Target = Target { os_name: "windows", os_version: "10.0", arch: "x86" };
+pub const LINUX_ARM64: Target = Target { os_name: "linux", os_version: "6.0", arch: "arm64" };
+pub const MACOS_ARM64: Target = Target { os_name: "osx", os_version: "13.0", arch: "arm64" };
+pub const WINDOWS_ARM64: Target = Target { os_name: "windows", os_version: "10.0", arch: "arm64" };
+
+/// Platforms the launcher can deliver a profile to.
+pub fn targets() -> Vec<(OsType, Target)> {
+    vec![
+        (OsType::LinuxX64, LINUX_X64),
+        (OsType::LinuxX32, LINUX_X86),
+        (OsType::MacOsX64, MACOS_X64),
+        (OsType::WindowsX64, WINDOWS_X64),
+        (OsType::WindowsX32, WINDOWS_X86),
+    ]
+}
+
+pub enum RuleOutcome {
+    Always,
+    Never,
+    /// Allowed only on some platforms, every rule is an alternative that on its own
+    /// selects the platforms where the library is allowed.
+    Only(Vec<LauncherRule>),
+}
+
+/// Mojang semantics: without rules everything is allowed, otherwise the last matching
+/// rule decides and nothing matching means disallowed.
+pub fn is_allowed(rules: &[Rule], target: &Target, features: &HashMap<String, bool>) -> bool {
+    if rules.is_empty() {
+        return true;
+    }
+    let mut allowed = false;
+    for rule in rules {
+        if rule_matches(rule, target, features) {
+            allowed = rule.action == RuleAction::Allow;
+        }
+    }
+    allowed
+}
+
+fn rule_matches(rule: &Rule, target: &Target, features: &HashMap<String, bool>) -> bool {
+    if let Some(os) = &rule.os {
+        if os.name.as_ref().map(|name| name != target.os_name).unwrap_or(false) {
+            return false;
+        }
+        if os.arch.as_ref().map(|arch| arch != target.arch).unwrap_or(false) {
+            return false;
+        }
+        if let Some(version) = &os.version {
+            let matches = Regex::new(version)
+                .map(|regex| regex.is_match(target.os_version))
+                .unwrap_or(false);
+            if !matches {
+                return false;
+            }
+        }
+    }
+    if let Some(required) = &rule.features {
+        let all_present = required
+            .iter()
+            .all(|(feature, value)| features.get(feature).copied().unwrap_or(false) == *value);
+        if !all_present {
+            return false;
+        }
+    }
+    true
+}
+
+pub fn evaluate(rules: &[Rule], features: &HashMap<String, bool>) -> RuleOutcome {
+    let (allowed, disallowed): (Vec<_>, Vec<_>) = targets()
+        .into_iter()
+        .partition(|(_, target)| is_allowed(rules, target, features));
+    if disallowed.is_empty() {
+        RuleOutcome::Always
+    } else if allowed.is_empty() {
+        RuleOutcome::Never
+    } else if disallowed.len() == 1 {
+        RuleOutcome::Only(vec![os_rule(disallowed.into_iter().next().unwrap().0, CompareMode::Unequal)])
+    } else {
+        RuleOutcome::Only(
+            allowed
+                .into_iter()
+                .map(|(os_type, _)| os_rule(os_type, CompareMode::Equal))
+                .collect(),
+        )
+    }
+}
+
+pub fn os_rule(os_type: OsType, compare_mode: CompareMode) -> LauncherRule {
+    LauncherRule::OsType(OsRule {
+        os_type,
+        compare_mode,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_rules(json: &str) -> Vec<Rule> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn no_rules_allow_everything() {
+        assert!(matches!(evaluate(&[], &HashMap::new()), RuleOutcome::Always));
+    }
+
+    #[test]
+    fn disallow_excludes_one_platform() {
+        let rules = parse_rules(r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#);
+        match evaluate(&rules, &HashMap::new()) {
+            RuleOutcome::Only(rules) => {
+                assert_eq!(rules.len(), 1);
+                assert!(matches!(
+                    &rules[0],
+                    LauncherRule::OsType(OsRule {
+                        os_type: OsType::MacOsX64,
+                        compare_mode: CompareMode::Unequal,
+                    })
+                ));
+            }
+            _ => panic!("expected platform rules"),
+        }
+    }
+
+    #[test]
+    fn allow_os_selects_its_platforms() {
+        let rules = parse_rules(r#"[{"action": "allow", "os": {"name": "windows"}}]"#);
+        match evaluate(&rules, &HashMap::new()) {
+            RuleOutcome::Only(rules) => {
+                assert_eq!(rules.len(), 2);
+                assert!(rules.iter().all(|rule| matches!(
+                    rule,
+                    LauncherRule::OsType(OsRule {
+                        os_type: OsType::WindowsX64,
+                        compare_mode: CompareMode::Equal,
+                    }) | LauncherRule::OsType(OsRule {
+                        os_type: OsType::WindowsX32,
+                        compare_mode: CompareMode::Equal,
+                    })
+                )));
+            }
+            _ => panic!("expected platform rules"),
+        }
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules = parse_rules(r#"[{"action": "disallow", "os": {"name": "linux"}}, {"action": "allow"}]"#);
+        assert!(matches!(evaluate(&rules, &HashMap::new()), RuleOutcome::Always));
+    }
+
+    #[test]
+    fn nothing_matching_disallows() {
+        let rules = parse_rules(r#"[{"action": "allow", "os": {"name": "plan9"}}]"#);
+        assert!(matches!(evaluate(&rules, &HashMap::new()), RuleOutcome::Never));
+    }
+
+    #[test]
+    fn os_version_is_a_regex() {
+        let rules = parse_rules(r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx", "version": "^10\\.5\\.\\d$"}}]"#);
+        assert!(matches!(evaluate(&rules, &HashMap::new()), RuleOutcome::Always));
+        let rules = parse_rules(r#"[{"action": "disallow", "os": {"name": "osx", "version": "^13\\."}}]"#);
+        assert!(!is_allowed(&rules, &MACOS_X64, &HashMap::new()));
+    }
+
+    #[test]
+    fn invalid_os_version_regex_never_matches() {
+        let rules = parse_rules(r#"[{"action": "allow", "os": {"version": "("}}]"#);
+        assert!(matches!(evaluate(&rules, &HashMap::new()), RuleOutcome::Never));
+    }
+
+    #[test]
+    fn arch_selects_32_bit_platforms() {
+        let rules = parse_rules(r#"[{"action": "allow", "os": {"arch": "x86"}}]"#);
+        assert!(is_allowed(&rules, &WINDOWS_X86, &HashMap::new()));
+        assert!(is_allowed(&rules, &LINUX_X86, &HashMap::new()));
+        assert!(!is_allowed(&rules, &WINDOWS_X64, &HashMap::new()));
+    }
+
+    #[test]
+    fn features_must_all_match() {
+        let rules = parse_rules(r#"[{"action": "allow", "features": {"is_demo_user": true, "has_custom_resolution": true}}]"#);
+        let mut features = HashMap::new();
+        features.insert("is_demo_user".to_string(), true);
+        assert!(!is_allowed(&rules, &LINUX_X64, &features));
+        features.insert("has_custom_resolution".to_string(), true);
+        assert!(is_allowed(&rules, &LINUX_X64, &features));
+        assert!(matches!(evaluate(&rules, &HashMap::new()), RuleOutcome::Never));
+    }
+}
@@ -1,12 +1,42 @@
 use crate::artifact::Artifact;
 use crate::minecraft::assets::Assets;
 use crate::minecraft::libraries::File as LibraryFile;
+use crate::download::Download;
 use launcher_extension_api::Result;
-use std::path::{Path, PathBuf};
+use sha1::{Digest, Sha1};
+use sha2::Sha512;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
 
-pub fn jar_url(base_path: &Path, file: &LibraryFile) -> (String, PathBuf) {
-    let url = file.url.clone();
-    (url, base_path.to_path_buf())
+pub fn jar_url(base_path: &Path, file: &LibraryFile) -> Result<Download> {
+    Ok(Download::to_folder(&file.url, base_path, file.sha1.as_deref())?.with_size(file.size))
+}
+
+pub fn file_sha1<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Applies the mirror with the longest matching url prefix.
+pub fn mirror_url(url: &str, mirrors: &BTreeMap<String, String>) -> String {
+    mirrors
+        .iter()
+        .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, mirror)| format!("{}{}", mirror, &url[prefix.len()..]))
+        .unwrap_or_else(|| url.to_string())
+}
+
+pub fn file_sha512<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha512::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 pub fn get_assets(url: &str) -> Result<Assets> {
@@ -36,3 +66,67 @@ pub fn get_yarn_path(version: &str) -> String {
         ver = version
     )
 }
+
+/// Rebuilds JSON objects with sorted keys, so maps serialized from a `HashMap` are
+/// written in the same order on every run.
+pub fn sorted_json(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let sorted: BTreeMap<String, Value> = map.into_iter().map(|(k, v)| (k, sorted_json(v))).collect();
+            Value::Object(sorted.into_iter().collect())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sorted_json).collect()),
+        value => value,
+    }
+}
+
+/// `group:artifact[:classifier]` without the version.
+pub fn artifact_key(name: &str) -> String {
+    match name.parse::<Artifact>() {
+        Ok(artifact) => match artifact.classifier {
+            Some(classifier) => format!("{}:{}:{}", artifact.group, artifact.artifact, classifier),
+            None => format!("{}:{}", artifact.group, artifact.artifact),
+        },
+        Err(_) => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirror_with_longest_prefix_wins() {
+        let mut mirrors = BTreeMap::new();
+        mirrors.insert("https://libraries.minecraft.net/".to_string(), "https://mirror.local/mojang/".to_string());
+        mirrors.insert(
+            "https://libraries.minecraft.net/org/lwjgl/".to_string(),
+            "https://mirror.local/lwjgl/".to_string(),
+        );
+        assert_eq!(
+            mirror_url("https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar", &mirrors),
+            "https://mirror.local/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"
+        );
+        assert_eq!(
+            mirror_url("https://libraries.minecraft.net/com/mojang/brigadier/1.1.8/brigadier-1.1.8.jar", &mirrors),
+            "https://mirror.local/mojang/com/mojang/brigadier/1.1.8/brigadier-1.1.8.jar"
+        );
+    }
+
+    #[test]
+    fn urls_without_mirror_are_kept() {
+        let mut mirrors = BTreeMap::new();
+        mirrors.insert("https://libraries.minecraft.net/".to_string(), "https://mirror.local/".to_string());
+        let url = "https://resources.download.minecraft.net/ab/abcdef";
+        assert_eq!(mirror_url(url, &mirrors), url);
+        assert_eq!(mirror_url(url, &BTreeMap::new()), url);
+    }
+
+    #[test]
+    fn prefix_must_match_from_the_start() {
+        let mut mirrors = BTreeMap::new();
+        mirrors.insert("libraries.minecraft.net/".to_string(), "https://mirror.local/".to_string());
+        let url = "https://libraries.minecraft.net/a/b/1/b-1.jar";
+        assert_eq!(mirror_url(url, &mirrors), url);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::download::MAX_RETRIES;
+
 pub fn correct_forge_version(val: &str) -> Result<(), String> {
     let url = format!(
         "https://meta.multimc.org/v1/net.minecraftforge/{version}.json",
@@ -13,6 +15,43 @@ pub fn correct_forge_version(val: &str) -> Result<(), String> {
     }
 }
 
+/// A local installer or a Forge version like `1.16.5-36.2.39`. The installer itself is
+/// only downloaded once the profile is generated.
+pub fn correct_forge_installer(val: &str) -> Result<(), String> {
+    let version = regex::Regex::new(r"^\d+\.\d+(\.\d+)?-\d+(\.\d+)+([-.][\w.-]+)?$").unwrap();
+    if std::path::Path::new(val).is_file() || version.is_match(val) {
+        Ok(())
+    } else {
+        Err(String::from("Incorrect forge installer"))
+    }
+}
+
+/// Checks the installer exists on the NeoForged maven without downloading it.
+pub fn correct_neoforge_version(val: &str) -> Result<(), String> {
+    let url = crate::minecraft::neoforge::installer_url(val);
+    if reqwest::blocking::Client::new()
+        .head(&url)
+        .send()
+        .map(|r| r.status() == 200)
+        .unwrap_or(false)
+    {
+        Ok(())
+    } else {
+        Err(String::from("Incorrect neoforge version"))
+    }
+}
+
+/// `latest` or a LiteLoader build like `1.12.2-SNAPSHOT`. Builds are looked up for the
+/// Minecraft version once the profile is generated.
+pub fn correct_liteloader_version(val: &str) -> Result<(), String> {
+    let version = regex::Regex::new(r"^\d+\.\d+(\.\d+)?([-_][\w.-]+)?$").unwrap();
+    if val == "latest" || version.is_match(val) {
+        Ok(())
+    } else {
+        Err(String::from("Incorrect liteloader version"))
+    }
+}
+
 pub fn correct_fabric_version(val: &str) -> Result<(), String> {
     let url = format!("https://maven.fabricmc.net/net/fabricmc/fabric-loader/{version}/fabric-loader-{version}.json", version = val);
     if reqwest::blocking::get(&url)
@@ -24,3 +63,53 @@ pub fn correct_fabric_version(val: &str) -> Result<(), String> {
         Err(String::from("Incorrect fabroc version"))
     }
 }
+
+pub fn correct_quilt_version(val: &str) -> Result<(), String> {
+    let url = format!("https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-loader/{version}/quilt-loader-{version}.json", version = val);
+    if reqwest::blocking::get(&url)
+        .map(|r| r.status() == 200)
+        .unwrap_or(false)
+    {
+        Ok(())
+    } else {
+        Err(String::from("Incorrect quilt version"))
+    }
+}
+
+pub fn correct_file(val: &str) -> Result<(), String> {
+    if std::path::Path::new(val).is_file() {
+        Ok(())
+    } else {
+        Err(format!("Can't find file {}", val))
+    }
+}
+
+pub fn correct_folder(val: &str) -> Result<(), String> {
+    if std::path::Path::new(val).is_dir() {
+        Ok(())
+    } else {
+        Err(format!("Can't find folder {}", val))
+    }
+}
+
+pub fn correct_path(val: &str) -> Result<(), String> {
+    if std::path::Path::new(val).exists() {
+        Ok(())
+    } else {
+        Err(format!("Can't find {}", val))
+    }
+}
+
+pub fn correct_workers(val: &str) -> Result<(), String> {
+    match val.parse::<usize>() {
+        Ok(workers) if workers > 0 => Ok(()),
+        _ => Err(String::from("Workers must be a positive number")),
+    }
+}
+
+pub fn correct_retries(val: &str) -> Result<(), String> {
+    match val.parse::<u32>() {
+        Ok(retries) if retries <= MAX_RETRIES => Ok(()),
+        _ => Err(format!("Retries must be a number from 0 to {}", MAX_RETRIES)),
+    }
+}